@@ -0,0 +1,25 @@
+-- Lists in display order, and their tasks as a tree of rows. `data` holds
+-- the fields of a task as JSON, without its subtasks.
+CREATE TABLE lists (
+    name TEXT PRIMARY KEY,
+    position INTEGER NOT NULL
+);
+
+CREATE TABLE tasks (
+    id INTEGER PRIMARY KEY,
+    list TEXT NOT NULL REFERENCES lists (name) ON DELETE CASCADE,
+    parent INTEGER REFERENCES tasks (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    data TEXT NOT NULL
+);
+
+CREATE INDEX tasks_parent ON tasks (list, parent, position);
+
+-- `revision` counts saves and is checked before each one, `current` is the
+-- current list and `history` the undo and redo stacks as JSON.
+CREATE TABLE meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+
+INSERT INTO meta (key, value) VALUES ('revision', '0'), ('current', 'inbox'), ('history', '{}');
@@ -0,0 +1,10 @@
+-- Tasks move through workflow states instead of being done or not: the
+-- `is_completed` flag becomes `state`, and the workflow is kept in `meta`.
+UPDATE tasks
+SET data = json_set(
+    json_remove(data, '$.is_completed'),
+    '$.state',
+    CASE WHEN json_extract(data, '$.is_completed') THEN 'done' ELSE 'todo' END
+);
+
+INSERT INTO meta (key, value) VALUES ('workflow', '["todo","in-progress","review","done"]');
@@ -0,0 +1,3 @@
+-- Completed tasks can be archived: top-level rows of a list's archive are
+-- flagged, their subtasks hang below them as usual.
+ALTER TABLE tasks ADD COLUMN archived INTEGER NOT NULL DEFAULT 0;
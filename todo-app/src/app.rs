@@ -0,0 +1,1510 @@
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use std::{collections::HashSet, fmt, fs, io, path::Path};
+
+use crate::{
+    archive::{self, Retention},
+    bulk::{self, BulkAction, Pick, Selection},
+    dates::Timezone,
+    dependencies::{Entry, TaskIndex},
+    history::{History, Operation},
+    lists::{self, TaskList},
+    stats::Stats,
+    storage::{Storage, StorageError, SyncReport},
+    task::{self, Attributes, Task},
+    timelog::{self, Effort, Report, WorkInterval},
+    tree::{self, TaskPath, TaskRef},
+    workflow::{self, Workflow},
+};
+
+#[derive(Debug)]
+pub enum TodoError {
+    InvalidIndex(TaskPath),
+    UnknownId(String),
+    UnknownList(String),
+    InvalidInput(String),
+    /// The task waits for open tasks, listed in the message.
+    Blocked(String),
+    History(String),
+    Io(io::Error),
+    Storage(StorageError),
+    /// `todo sync` could not exchange changes with the remote.
+    Sync(StorageError),
+}
+
+impl fmt::Display for TodoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TodoError::InvalidIndex(index) => write!(f, "No task with index {index}"),
+            TodoError::UnknownId(id) => write!(f, "No task with id #{id}"),
+            TodoError::UnknownList(name) => write!(f, "No list named {name}"),
+            TodoError::InvalidInput(msg) => write!(f, "{msg}"),
+            TodoError::Blocked(msg) => write!(f, "{msg}"),
+            TodoError::History(msg) => write!(f, "{msg}"),
+            TodoError::Io(err) => write!(f, "{err}"),
+            TodoError::Storage(err) => write!(f, "Failed to save tasks: {err}"),
+            TodoError::Sync(err) => write!(f, "Failed to sync tasks: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for TodoError {}
+
+impl From<StorageError> for TodoError {
+    fn from(err: StorageError) -> Self {
+        TodoError::Storage(err)
+    }
+}
+
+pub struct TodoApp {
+    lists: Vec<TaskList>,
+    /// The list tasks are added to and looked up in during this run.
+    current: String,
+    /// The list saved as current, used again on the next run.
+    default_list: String,
+    history: History,
+    workflow: Workflow,
+    timezone: Timezone,
+    /// Tasks done for this many days are archived with the next change.
+    archive_after: Option<u64>,
+    store: Box<dyn Storage>,
+}
+
+impl TodoApp {
+    pub fn new(mut store: Box<dyn Storage>, timezone: Timezone) -> Result<Self, StorageError> {
+        let data = store.load()?;
+
+        let mut todo_app = Self {
+            lists: data.lists,
+            current: data.current.clone(),
+            default_list: data.current,
+            history: data.history,
+            workflow: data.workflow,
+            timezone,
+            archive_after: None,
+            store,
+        };
+        todo_app.follow_renames(Vec::new());
+        todo_app.assign_ids()?;
+
+        Ok(todo_app)
+    }
+
+    /// Saves the lists. If another process saved in the meantime, nothing is
+    /// written and its version is loaded instead, dropping the change in memory.
+    fn save(&mut self) -> Result<(), TodoError> {
+        match self.store.save(
+            &self.lists,
+            &self.default_list,
+            &self.history,
+            &self.workflow,
+        ) {
+            Err(StorageError::Conflict) => {
+                self.reload()?;
+                Err(TodoError::Storage(StorageError::Conflict))
+            }
+            result => Ok(result?),
+        }
+    }
+
+    /// Lets stores that keep a log of changes, like the git store, record the last save.
+    fn commit(&mut self, message: &str) -> Result<(), TodoError> {
+        Ok(self.store.commit(message)?)
+    }
+
+    /// Exchanges changes with `remote` and loads the merged tasks.
+    pub fn sync(&mut self, remote: &str) -> Result<SyncReport, TodoError> {
+        let result = self.store.sync(remote);
+        // A failed sync may still have pulled, or left the file as it was
+        self.reload()?;
+
+        result.map_err(TodoError::Sync)
+    }
+
+    pub fn is_encrypted(&self) -> Result<bool, TodoError> {
+        Ok(self.store.is_encrypted()?)
+    }
+
+    /// Encrypts the data file with `passphrase`, or replaces the passphrase
+    /// it is encrypted with, and saves it.
+    pub fn encrypt(&mut self, passphrase: &str) -> Result<(), TodoError> {
+        if passphrase.is_empty() {
+            return Err(TodoError::InvalidInput(
+                "The passphrase cannot be empty".to_string(),
+            ));
+        }
+
+        self.store.set_passphrase(Some(passphrase))?;
+        self.save()
+    }
+
+    /// Writes the data file in the clear again.
+    pub fn decrypt(&mut self) -> Result<(), TodoError> {
+        self.store.set_passphrase(None)?;
+        self.save()
+    }
+
+    pub fn reload(&mut self) -> Result<(), StorageError> {
+        let data = self.store.load()?;
+
+        self.lists = data.lists;
+        self.default_list = data.current;
+        self.history = data.history;
+        self.workflow = data.workflow;
+        self.follow_renames(Vec::new());
+
+        Ok(())
+    }
+
+    /// Saves ids given to tasks stored without one, so they stay the same
+    /// from the first run on.
+    fn assign_ids(&mut self) -> Result<(), StorageError> {
+        let mut changed = false;
+        for list in &mut self.lists {
+            changed |= tree::assign_ids(&mut list.tasks);
+        }
+
+        if changed {
+            self.store.save(
+                &self.lists,
+                &self.default_list,
+                &self.history,
+                &self.workflow,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The wall-clock time in the configured timezone, which due dates are given in.
+    pub fn now(&self) -> NaiveDateTime {
+        self.timezone.now()
+    }
+
+    pub fn timezone(&self) -> Timezone {
+        self.timezone
+    }
+
+    pub fn lists(&self) -> &[TaskList] {
+        &self.lists
+    }
+
+    /// The name of the list worked on.
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+
+    /// The list saved as current, which later runs start in.
+    pub fn default_list(&self) -> &str {
+        &self.default_list
+    }
+
+    /// The data file or database the tasks are kept in.
+    pub fn path(&self) -> &Path {
+        self.store.path()
+    }
+
+    /// The revision of the data file the tasks were loaded from or saved as last.
+    pub fn version(&self) -> u64 {
+        self.store.revision()
+    }
+
+    pub fn workflow(&self) -> &Workflow {
+        &self.workflow
+    }
+
+    /// Tasks of the current list.
+    pub fn items(&self) -> &[Task] {
+        self.lists
+            .iter()
+            .find(|list| list.name == self.current)
+            .map(|list| list.tasks.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Archived tasks of the current list, in the order they were archived.
+    pub fn archived(&self) -> &[Task] {
+        self.lists
+            .iter()
+            .find(|list| list.name == self.current)
+            .map(|list| list.archive.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Applies a change to the lists, records it for undo and saves.
+    fn apply(&mut self, operation: Operation) -> Result<(), TodoError> {
+        operation
+            .apply(&mut self.lists)
+            .map_err(TodoError::History)?;
+        self.follow_renames(operation.renames());
+        let operation = self.archive_along(operation)?;
+        let message = format!("todo: {}", operation.describe());
+        self.history.record(operation);
+        self.save()?;
+        self.commit(&message)
+    }
+
+    /// Archives the tasks done for `archive_after` days as part of a change
+    /// just applied, so undoing the change brings them back as well and
+    /// undo and redo never archive on their own.
+    fn archive_along(&mut self, operation: Operation) -> Result<Operation, TodoError> {
+        let Some(days) = self.archive_after else {
+            return Ok(operation);
+        };
+        // A task just restored stays out, however long ago it was completed
+        let restored = restored_ids(&operation);
+        let archived = self.archive_operations(true, Some(days), &restored);
+        if archived.is_empty() {
+            return Ok(operation);
+        }
+
+        let mut operations = match operation {
+            Operation::Batch { operations } => operations,
+            operation => vec![operation],
+        };
+        for archive in archived {
+            archive.apply(&mut self.lists).map_err(TodoError::History)?;
+            operations.push(archive);
+        }
+        Ok(Operation::Batch { operations })
+    }
+
+    pub fn undo(&mut self) -> Result<Operation, TodoError> {
+        let operation = self
+            .history
+            .undo(&mut self.lists)
+            .map_err(TodoError::History)?;
+        self.follow_renames(operation.inverse().renames());
+        self.save()?;
+        self.commit(&format!("todo: undo {}", operation.describe()))?;
+
+        Ok(operation)
+    }
+
+    pub fn redo(&mut self) -> Result<Operation, TodoError> {
+        let operation = self
+            .history
+            .redo(&mut self.lists)
+            .map_err(TodoError::History)?;
+        self.follow_renames(operation.renames());
+        self.save()?;
+        self.commit(&format!("todo: redo {}", operation.describe()))?;
+
+        Ok(operation)
+    }
+
+    /// Keeps the current and default list pointing at the same list after it
+    /// was renamed, and falls back to the first list after it was deleted.
+    fn follow_renames(&mut self, renames: Vec<(String, String)>) {
+        for (from, to) in renames {
+            for name in [&mut self.current, &mut self.default_list] {
+                if *name == from {
+                    *name = to.clone();
+                }
+            }
+        }
+
+        if self.lists.is_empty() {
+            self.lists.push(TaskList::new(lists::DEFAULT_LIST));
+        }
+        for name in [&mut self.current, &mut self.default_list] {
+            if lists::position(&self.lists, name).is_none() {
+                *name = self.lists[0].name.clone();
+            }
+        }
+    }
+
+    pub fn task(&self, path: &TaskPath) -> Result<&Task, TodoError> {
+        tree::find(self.items(), path).ok_or_else(|| TodoError::InvalidIndex(path.clone()))
+    }
+
+    /// The list and path of a task picked by index in the current list, or by id in any list.
+    pub fn locate(&self, task: &TaskRef) -> Result<(String, TaskPath), TodoError> {
+        match task {
+            TaskRef::Path(path) => {
+                self.task(path)?;
+                Ok((self.current.clone(), path.clone()))
+            }
+            TaskRef::Id(prefix) => {
+                let index = TaskIndex::new(&self.lists);
+                match index.find(prefix).as_slice() {
+                    [entry] => Ok((entry.list.to_string(), entry.path.clone())),
+                    [] => Err(TodoError::UnknownId(prefix.clone())),
+                    _ => Err(TodoError::InvalidInput(format!(
+                        "Several tasks have an id starting with #{prefix}, please give more of it"
+                    ))),
+                }
+            }
+        }
+    }
+
+    /// The path of a picked task. A task picked by id in another list makes
+    /// that list the one worked on, as with `--list`.
+    pub fn resolve(&mut self, task: &TaskRef) -> Result<TaskPath, TodoError> {
+        let (list, path) = self.locate(task)?;
+        self.select(&list)?;
+        Ok(path)
+    }
+
+    fn list_position(&self, name: &str) -> Result<usize, TodoError> {
+        lists::position(&self.lists, name).ok_or_else(|| TodoError::UnknownList(name.to_string()))
+    }
+
+    /// Works on another list for this run only, as with `--list`.
+    pub fn select(&mut self, name: &str) -> Result<(), TodoError> {
+        self.list_position(name)?;
+        self.current = name.to_string();
+        Ok(())
+    }
+
+    /// Makes `name` the current list for this and later runs.
+    pub fn switch_list(&mut self, name: &str) -> Result<(), TodoError> {
+        self.select(name)?;
+        self.default_list = name.to_string();
+        self.save()
+    }
+
+    pub fn create_list(&mut self, name: &str) -> Result<(), TodoError> {
+        lists::validate_name(&self.lists, name).map_err(TodoError::InvalidInput)?;
+
+        self.apply(Operation::InsertList {
+            position: self.lists.len(),
+            list: TaskList::new(name),
+        })
+    }
+
+    pub fn rename_list(&mut self, from: &str, to: &str) -> Result<(), TodoError> {
+        self.list_position(from)?;
+        lists::validate_name(&self.lists, to).map_err(TodoError::InvalidInput)?;
+
+        self.apply(Operation::RenameList {
+            from: from.to_string(),
+            to: to.to_string(),
+        })
+    }
+
+    /// Deletes a list with all its tasks. The last remaining list cannot be deleted.
+    pub fn delete_list(&mut self, name: &str) -> Result<TaskList, TodoError> {
+        let position = self.list_position(name)?;
+
+        if self.lists.len() == 1 {
+            return Err(TodoError::InvalidInput(
+                "Cannot delete the only list".to_string(),
+            ));
+        }
+
+        let list = self.lists[position].clone();
+        self.apply(Operation::RemoveList {
+            position,
+            list: list.clone(),
+        })?;
+
+        Ok(list)
+    }
+
+    /// Moves a task with its subtasks to the end of another list.
+    pub fn move_task(&mut self, path: &TaskPath, to: &str) -> Result<TaskPath, TodoError> {
+        let target = self.list_position(to)?;
+        let task = self.task(path)?.clone();
+
+        if to == self.current {
+            return Err(TodoError::InvalidInput(format!(
+                "Task is already in list {to}"
+            )));
+        }
+
+        let destination = TaskPath::top(self.lists[target].tasks.len());
+        self.apply(Operation::Batch {
+            operations: vec![
+                Operation::Remove {
+                    list: self.current.clone(),
+                    path: path.clone(),
+                    task: task.clone(),
+                },
+                Operation::Insert {
+                    list: to.to_string(),
+                    path: destination.clone(),
+                    task,
+                },
+            ],
+        })?;
+
+        Ok(destination)
+    }
+
+    /// Adds a task at the end of the list, or as the last subtask of `parent`.
+    pub fn add(&mut self, input: &str, parent: Option<&TaskPath>) -> Result<TaskPath, TodoError> {
+        let attributes = Attributes::parse(input, self.now()).map_err(TodoError::InvalidInput)?;
+        let path = match parent {
+            Some(parent) => parent.child(self.task(parent)?.subtasks.len()),
+            None => TaskPath::top(self.items().len()),
+        };
+
+        self.apply(Operation::Insert {
+            list: self.current.clone(),
+            path: path.clone(),
+            task: Task::new(attributes),
+        })?;
+
+        Ok(path)
+    }
+
+    /// Removes a task together with its subtasks.
+    pub fn remove(&mut self, path: &TaskPath) -> Result<Task, TodoError> {
+        let removed_task = self.task(path)?.clone();
+
+        self.apply(Operation::Remove {
+            list: self.current.clone(),
+            path: path.clone(),
+            task: removed_task.clone(),
+        })?;
+
+        Ok(removed_task)
+    }
+
+    /// Builds the operation that applies `change` to the task at `path`.
+    fn replacement(
+        &self,
+        path: &TaskPath,
+        change: impl FnOnce(&mut Task),
+    ) -> Result<Operation, TodoError> {
+        self.replacement_in(&self.current, path, change)
+    }
+
+    /// Like `replacement`, for a task in any list.
+    fn replacement_in(
+        &self,
+        list: &str,
+        path: &TaskPath,
+        change: impl FnOnce(&mut Task),
+    ) -> Result<Operation, TodoError> {
+        let position = self.list_position(list)?;
+        let before = tree::find(&self.lists[position].tasks, path)
+            .ok_or_else(|| TodoError::InvalidIndex(path.clone()))?
+            .clone();
+        let mut after = before.clone();
+        change(&mut after);
+
+        Ok(Operation::Replace {
+            list: list.to_string(),
+            path: path.clone(),
+            before: Box::new(before),
+            after: Box::new(after),
+        })
+    }
+
+    /// Tasks with a running timer, in any list.
+    pub fn running(&self) -> Vec<(String, TaskPath)> {
+        self.lists
+            .iter()
+            .flat_map(|list| {
+                tree::flatten(&list.tasks)
+                    .into_iter()
+                    .filter(|(_, task)| task.is_running())
+                    .map(|(path, _)| (list.name.clone(), path))
+            })
+            .collect()
+    }
+
+    /// Starts the timer of a task. Any other running timer is stopped, so
+    /// the same time is never booked on two tasks.
+    pub fn start_timer(&mut self, path: &TaskPath) -> Result<(), TodoError> {
+        if self.task(path)?.is_running() {
+            return Err(TodoError::InvalidInput(format!(
+                "The timer of task {path} is already running"
+            )));
+        }
+
+        let now = Local::now();
+        let mut operations = Vec::new();
+        for (list, running) in self.running() {
+            operations.push(self.replacement_in(&list, &running, |task| task.stop(now))?);
+        }
+        operations.push(self.replacement(path, |task| task.start(now))?);
+
+        self.apply(Operation::Batch { operations })
+    }
+
+    /// Stops the timer of a task, or every running timer. Returns how many were stopped.
+    pub fn stop_timer(&mut self, path: Option<&TaskPath>) -> Result<usize, TodoError> {
+        let now = Local::now();
+        let running = match path {
+            Some(path) if self.task(path)?.is_running() => {
+                vec![(self.current.clone(), path.clone())]
+            }
+            Some(path) => {
+                return Err(TodoError::InvalidInput(format!(
+                    "The timer of task {path} is not running"
+                )))
+            }
+            None => self.running(),
+        };
+
+        if running.is_empty() {
+            return Err(TodoError::InvalidInput("No timer is running".to_string()));
+        }
+
+        let mut operations = Vec::new();
+        for (list, path) in &running {
+            operations.push(self.replacement_in(list, path, |task| task.stop(now))?);
+        }
+
+        self.apply(Operation::Batch { operations })?;
+        Ok(running.len())
+    }
+
+    /// Books work done without the timer: ending now, or starting at midnight of `date`.
+    pub fn log_work(
+        &mut self,
+        path: &TaskPath,
+        effort: Effort,
+        date: Option<NaiveDate>,
+    ) -> Result<(), TodoError> {
+        let interval = match date {
+            Some(date) => {
+                let start = timelog::start_of_day(date)
+                    .ok_or_else(|| TodoError::InvalidInput(format!("Invalid date: {date}")))?;
+                WorkInterval {
+                    start,
+                    end: Some(start + effort.delta()),
+                }
+            }
+            None => {
+                let end = Local::now();
+                WorkInterval {
+                    start: end - effort.delta(),
+                    end: Some(end),
+                }
+            }
+        };
+
+        self.replace_with(path, |task| {
+            task.work.push(interval);
+            task.work.sort_by_key(|interval| interval.start);
+        })
+    }
+
+    /// Holds back reminders for a task for the given time.
+    pub fn snooze(&mut self, path: &TaskPath, effort: Effort) -> Result<(), TodoError> {
+        let until = Local::now() + effort.delta();
+        self.replace_with(path, |task| task.snoozed_until = Some(until))
+    }
+
+    /// Time logged on the tasks of every list and archive between two days, inclusive.
+    pub fn time_report(&self, from: NaiveDate, to: NaiveDate) -> Report {
+        let tasks = self.lists.iter().flat_map(|list| {
+            let archived = tree::flatten(&list.archive)
+                .into_iter()
+                .map(move |(path, task)| {
+                    let label = format!("{} archive {path}: {}", list.name, task.description);
+                    (label, task)
+                });
+
+            tree::flatten(&list.tasks)
+                .into_iter()
+                .map(move |(path, task)| {
+                    (format!("{} {path}: {}", list.name, task.description), task)
+                })
+                .chain(archived)
+        });
+
+        Report::new(tasks, from, to, Local::now())
+    }
+
+    /// Completion stats of the current list, or of every list, from `from` to
+    /// `to`. Archived tasks count too.
+    pub fn stats(&self, from: NaiveDate, to: NaiveDate, all: bool) -> Stats {
+        let tasks = self
+            .lists
+            .iter()
+            .filter(|list| all || list.name == self.current)
+            .flat_map(|list| {
+                tree::flatten(&list.tasks)
+                    .into_iter()
+                    .chain(tree::flatten(&list.archive))
+            })
+            .map(|(_, task)| task);
+
+        Stats::new(tasks, from, to, self.now(), self.timezone)
+    }
+
+    fn replace_with(
+        &mut self,
+        path: &TaskPath,
+        change: impl FnOnce(&mut Task),
+    ) -> Result<(), TodoError> {
+        let operation = self.replacement(path, change)?;
+        self.apply(operation)
+    }
+
+    /// Completes a task. For a recurring task this also adds the next
+    /// occurrence right after it, as a single undoable change. A task that
+    /// waits for open tasks is only completed with `force`.
+    pub fn complete(&mut self, path: &TaskPath, force: bool) -> Result<(), TodoError> {
+        let task = self.task(path)?;
+
+        if !force && !task.is_completed() {
+            let blockers = self.blocker_labels(task);
+            if !blockers.is_empty() {
+                return Err(TodoError::Blocked(format!(
+                    "Task {path} is blocked by {}",
+                    blockers.join(", ")
+                )));
+            }
+        }
+
+        let next = if task.is_completed() {
+            None
+        } else {
+            task.next_occurrence(self.now().date())
+        };
+
+        let completion = self.replacement(path, Task::complete)?;
+
+        match next {
+            Some(next) => self.apply(Operation::Batch {
+                operations: vec![
+                    completion,
+                    Operation::Insert {
+                        list: self.current.clone(),
+                        path: path.next_sibling(),
+                        task: next,
+                    },
+                ],
+            }),
+            None => self.apply(completion),
+        }
+    }
+
+    /// Moves a task to a state of the workflow. Moving it to `done` completes
+    /// it, which a blocked task only allows with `force`.
+    pub fn set_state(
+        &mut self,
+        path: &TaskPath,
+        state: &str,
+        force: bool,
+    ) -> Result<(), TodoError> {
+        let state = self
+            .workflow
+            .find(state)
+            .map_err(TodoError::InvalidInput)?
+            .to_string();
+
+        if state == workflow::DONE {
+            return self.complete(path, force);
+        }
+        if self.task(path)?.state == state {
+            return Err(TodoError::InvalidInput(format!(
+                "Task {path} is already in {state}"
+            )));
+        }
+
+        self.replace_with(path, |task| task.set_state(&state))
+    }
+
+    /// Moves a task on to the next state of the workflow and returns it.
+    pub fn advance(&mut self, path: &TaskPath, force: bool) -> Result<String, TodoError> {
+        let task = self.task(path)?;
+        let state = self
+            .workflow
+            .next(&task.state)
+            .ok_or_else(|| TodoError::InvalidInput(format!("Task {path} is already done")))?
+            .to_string();
+
+        self.set_state(path, &state, force)?;
+        Ok(state)
+    }
+
+    /// Moves a task back to the previous state of the workflow and returns it.
+    pub fn retreat(&mut self, path: &TaskPath) -> Result<String, TodoError> {
+        let task = self.task(path)?;
+        let state = self
+            .workflow
+            .previous(&task.state)
+            .ok_or_else(|| {
+                TodoError::InvalidInput(format!("Task {path} is in the first state already"))
+            })?
+            .to_string();
+
+        self.set_state(path, &state, false)?;
+        Ok(state)
+    }
+
+    /// Replaces the states between `todo` and `done`. States that still hold
+    /// tasks cannot be left out.
+    pub fn set_workflow(&mut self, between: &[String]) -> Result<(), TodoError> {
+        let workflow = Workflow::new(between).map_err(TodoError::InvalidInput)?;
+
+        let mut in_use: Vec<&str> = self
+            .lists
+            .iter()
+            .flat_map(|list| tree::flatten(&list.tasks))
+            .map(|(_, task)| task.state.as_str())
+            .filter(|state| workflow.find(state).is_err())
+            .collect();
+        in_use.sort_unstable();
+        in_use.dedup();
+        if !in_use.is_empty() {
+            return Err(TodoError::InvalidInput(format!(
+                "Tasks are still in {}, move them to another state first",
+                in_use.join(", ")
+            )));
+        }
+
+        self.workflow = workflow;
+        self.save()?;
+        self.commit(&format!(
+            "todo: set workflow to {}",
+            self.workflow.states().join(", ")
+        ))
+    }
+
+    /// Marks an open task as done, or reopens a completed one.
+    pub fn toggle(&mut self, path: &TaskPath) -> Result<(), TodoError> {
+        if self.task(path)?.is_completed() {
+            self.replace_with(path, Task::reopen)
+        } else {
+            self.complete(path, false)
+        }
+    }
+
+    /// Labels of the open tasks that `task`, or one of its open subtasks,
+    /// waits for: their index, prefixed with the list when it is another one.
+    pub fn blocker_labels(&self, task: &Task) -> Vec<String> {
+        let index = TaskIndex::new(&self.lists);
+        let mut labels: Vec<String> = tree::flatten(std::slice::from_ref(task))
+            .into_iter()
+            .filter(|(_, task)| !task.is_completed())
+            .flat_map(|(_, task)| index.blockers(task))
+            .map(|entry| self.label(entry.list, &entry.path))
+            .collect();
+
+        labels.dedup();
+        labels
+    }
+
+    /// Labels of the open tasks `task` itself waits for.
+    pub fn blocked_by(&self, index: &TaskIndex, task: &Task) -> Vec<String> {
+        index
+            .blockers(task)
+            .into_iter()
+            .map(|entry| self.label(entry.list, &entry.path))
+            .collect()
+    }
+
+    /// How a task is shown in messages: its index, with the list if it is not the current one.
+    pub fn label(&self, list: &str, path: &TaskPath) -> String {
+        if list == self.current {
+            path.to_string()
+        } else {
+            format!("{list} {path}")
+        }
+    }
+
+    /// The id of a task picked by index in the current list or by id in any
+    /// list, with its list and path.
+    fn locate_id(&self, task: &TaskRef) -> Result<(String, TaskPath, String), TodoError> {
+        let (list, path) = self.locate(task)?;
+        let position = self.list_position(&list)?;
+        let id = tree::find(&self.lists[position].tasks, &path)
+            .map(|task| task.id.clone())
+            .ok_or_else(|| TodoError::InvalidIndex(path.clone()))?;
+
+        Ok((list, path, id))
+    }
+
+    /// Makes the task at `path` in the current list wait for the tasks `on`,
+    /// which can be in any list when picked by id, as a single change.
+    pub fn add_dependencies(&mut self, path: &TaskPath, on: &[TaskRef]) -> Result<(), TodoError> {
+        let task = self.task(path)?;
+        let index = TaskIndex::new(&self.lists);
+        let mut depends_on = task.depends_on.clone();
+
+        for on in on {
+            let (list, on_path, id) = self.locate_id(on)?;
+            if depends_on.contains(&id) {
+                return Err(TodoError::InvalidInput(format!(
+                    "Task {path} already depends on task {on}"
+                )));
+            }
+            // A parent is only done when its subtasks are, so they cannot wait for it
+            if list == self.current && (on_path == *path || on_path.is_ancestor_of(path)) {
+                return Err(TodoError::InvalidInput(format!(
+                    "Task {path} cannot depend on task {on}"
+                )));
+            }
+            // The new dependencies all start at this task, so they cannot
+            // close a cycle among themselves
+            if index.creates_cycle(&task.id, &id) {
+                return Err(TodoError::InvalidInput(format!(
+                    "Task {on} already depends on task {path}, directly or through other tasks"
+                )));
+            }
+            depends_on.push(id);
+        }
+
+        self.replace_with(path, |task| task.depends_on = depends_on)
+    }
+
+    /// Stops the task at `path` waiting for the tasks `on`, as a single change.
+    pub fn remove_dependencies(
+        &mut self,
+        path: &TaskPath,
+        on: &[TaskRef],
+    ) -> Result<(), TodoError> {
+        let mut depends_on = self.task(path)?.depends_on.clone();
+
+        for on in on {
+            let (_, _, id) = self.locate_id(on)?;
+            if !depends_on.contains(&id) {
+                return Err(TodoError::InvalidInput(format!(
+                    "Task {path} does not depend on task {on}"
+                )));
+            }
+            depends_on.retain(|other| *other != id);
+        }
+
+        self.replace_with(path, |task| task.depends_on = depends_on)
+    }
+
+    /// Open tasks that can be worked on now: not blocked and without open
+    /// subtasks. Overdue and soon due tasks come first, then by priority.
+    pub fn actionable<'a>(&'a self, tasks: &'a [Task]) -> Vec<(TaskPath, &'a Task)> {
+        let index = TaskIndex::new(&self.lists);
+        let mut actionable: Vec<_> = tree::flatten(tasks)
+            .into_iter()
+            .filter(|(_, task)| {
+                !task.is_completed()
+                    && !index.is_blocked(task)
+                    && task.subtasks.iter().all(|subtask| subtask.is_completed())
+            })
+            .collect();
+
+        actionable.sort_by(|(_, a), (_, b)| {
+            let due = |task: &Task| task.due.map(|due| due.deadline());
+            match (due(a), due(b)) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            }
+            .then_with(|| b.priority.cmp(&a.priority))
+        });
+
+        actionable
+    }
+
+    /// The tasks a selection picks, each once, in the order given. Indices
+    /// and filters pick from the current list, ids from any list.
+    pub fn pick(&self, selection: &Selection) -> Result<Vec<Entry<'_>>, TodoError> {
+        let entry = |list: &str, path: &TaskPath| -> Result<Entry<'_>, TodoError> {
+            let list = &self.lists[self.list_position(list)?];
+            let task = tree::find(&list.tasks, path)
+                .ok_or_else(|| TodoError::InvalidIndex(path.clone()))?;
+
+            Ok(Entry {
+                list: &list.name,
+                path: path.clone(),
+                task,
+            })
+        };
+
+        let mut picked = Vec::new();
+        match selection {
+            Selection::Filter(query) => {
+                for (path, _) in query.apply(self.items(), self.now()) {
+                    picked.push(entry(&self.current, &path)?);
+                }
+            }
+            Selection::Tasks(picks) => {
+                for pick in picks {
+                    match pick {
+                        Pick::Task(task) => {
+                            let (list, path) = self.locate(task)?;
+                            picked.push(entry(&list, &path)?);
+                        }
+                        Pick::Range(first, last) => {
+                            for path in bulk::siblings(first, last) {
+                                picked.push(entry(&self.current, &path)?);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut seen = HashSet::new();
+        picked.retain(|entry| seen.insert(entry.task.id.clone()));
+        Ok(picked)
+    }
+
+    /// Applies `action` to the tasks with the given ids as a single undoable
+    /// change, and returns how many tasks it changed. Tasks the action would
+    /// not change, e.g. completed ones for `Complete`, are left out. Blocked
+    /// tasks are only completed with `force`, unless what they wait for is
+    /// completed with them.
+    pub fn bulk(
+        &mut self,
+        ids: &[String],
+        action: &BulkAction,
+        force: bool,
+    ) -> Result<usize, TodoError> {
+        if let BulkAction::Move(to) = action {
+            self.list_position(to)?;
+        }
+        if *action == BulkAction::Complete && !force {
+            self.check_blockers(ids)?;
+        }
+
+        // Each step is built against the lists as the steps before it left
+        // them, as paths shift when tasks are removed or inserted
+        let mut staged = self.lists.clone();
+        let mut operations = Vec::new();
+        let mut changed = 0;
+
+        for id in ids {
+            let Some((list, path, task)) = find_by_id(&staged, id) else {
+                // Deleted or moved along with a parent earlier in the batch
+                continue;
+            };
+
+            let steps = bulk_steps(&staged, &list, &path, &task, action, self.now().date());
+            if steps.is_empty() {
+                continue;
+            }
+            for step in &steps {
+                step.apply(&mut staged).map_err(TodoError::History)?;
+            }
+            operations.extend(steps);
+            changed += 1;
+        }
+
+        if operations.is_empty() {
+            return Err(TodoError::InvalidInput(
+                "None of the tasks would change".to_string(),
+            ));
+        }
+
+        self.apply(Operation::Batch { operations })?;
+        Ok(changed)
+    }
+
+    /// Fails with the tasks among `ids` that wait for open tasks not completed with them.
+    fn check_blockers(&self, ids: &[String]) -> Result<(), TodoError> {
+        let index = TaskIndex::new(&self.lists);
+
+        let blocked: Vec<String> = ids
+            .iter()
+            .filter_map(|id| index.get(id))
+            .filter(|entry| !entry.task.is_completed())
+            .filter_map(|entry| {
+                let blockers: Vec<String> = tree::flatten(std::slice::from_ref(entry.task))
+                    .into_iter()
+                    .filter(|(_, task)| !task.is_completed())
+                    .flat_map(|(_, task)| index.blockers(task))
+                    .filter(|blocker| !ids.contains(&blocker.task.id))
+                    .map(|blocker| self.label(blocker.list, &blocker.path))
+                    .collect();
+
+                (!blockers.is_empty()).then(|| {
+                    format!(
+                        "task {} is blocked by {}",
+                        self.label(entry.list, &entry.path),
+                        blockers.join(", ")
+                    )
+                })
+            })
+            .collect();
+
+        if blocked.is_empty() {
+            Ok(())
+        } else {
+            Err(TodoError::Blocked(format!(
+                "Cannot complete all tasks: {}",
+                blocked.join("; ")
+            )))
+        }
+    }
+
+    /// Appends imported tasks to the list as a single undoable change.
+    pub fn import(&mut self, tasks: Vec<Task>) -> Result<usize, TodoError> {
+        if tasks.is_empty() {
+            return Err(TodoError::InvalidInput(
+                "No tasks found to import".to_string(),
+            ));
+        }
+
+        for (_, task) in tree::flatten(&tasks) {
+            self.workflow
+                .find(&task.state)
+                .map_err(TodoError::InvalidInput)?;
+        }
+
+        let count = tasks.len();
+        let operations = tasks
+            .into_iter()
+            .enumerate()
+            .map(|(offset, task)| Operation::Insert {
+                list: self.current.clone(),
+                path: TaskPath::top(self.items().len() + offset),
+                task,
+            })
+            .collect();
+
+        self.apply(Operation::Batch { operations })?;
+        Ok(count)
+    }
+
+    /// Replaces the Markdown note of a task; an empty note removes it.
+    /// Returns whether the note changed.
+    pub fn set_note(&mut self, path: &TaskPath, note: &str) -> Result<bool, TodoError> {
+        // Editors end files with a newline
+        let note = note.trim_end();
+        if self.task(path)?.note == note {
+            return Ok(false);
+        }
+
+        self.replace_with(path, |task| task.note = note.to_string())?;
+        Ok(true)
+    }
+
+    /// Adds URLs or files to a task as a single change. Files have to exist
+    /// and are kept by their absolute path, so they are found from any
+    /// directory. Returns the attachments as stored.
+    pub fn attach<S: AsRef<str>>(
+        &mut self,
+        path: &TaskPath,
+        targets: &[S],
+    ) -> Result<Vec<String>, TodoError> {
+        let mut attachments = self.task(path)?.attachments.clone();
+        let mut stored = Vec::new();
+
+        for target in targets.iter().map(AsRef::as_ref) {
+            let attachment = if task::is_url(target) {
+                target.to_string()
+            } else {
+                fs::canonicalize(target)
+                    .map_err(|err| {
+                        TodoError::InvalidInput(format!("Cannot attach {target}: {err}"))
+                    })?
+                    .to_string_lossy()
+                    .into_owned()
+            };
+
+            if attachments.contains(&attachment) {
+                return Err(TodoError::InvalidInput(format!(
+                    "Task {path} already has {attachment} attached"
+                )));
+            }
+            attachments.push(attachment.clone());
+            stored.push(attachment);
+        }
+
+        self.replace_with(path, |task| task.attachments = attachments)?;
+        Ok(stored)
+    }
+
+    /// Removes attachments from a task as a single change, each picked by
+    /// its number in the task details, as stored, or by a path to the same
+    /// file. Returns them.
+    pub fn detach<S: AsRef<str>>(
+        &mut self,
+        path: &TaskPath,
+        targets: &[S],
+    ) -> Result<Vec<String>, TodoError> {
+        let attachments = &self.task(path)?.attachments;
+        let mut positions = Vec::new();
+
+        // Numbers refer to the attachments as they were before any is removed
+        for target in targets.iter().map(AsRef::as_ref) {
+            let canonical = fs::canonicalize(target)
+                .ok()
+                .map(|file| file.to_string_lossy().into_owned());
+
+            let position = target
+                .parse::<usize>()
+                .ok()
+                .filter(|number| (1..=attachments.len()).contains(number))
+                .map(|number| number - 1)
+                .or_else(|| {
+                    attachments.iter().position(|attachment| {
+                        attachment == target || canonical.as_ref() == Some(attachment)
+                    })
+                })
+                .filter(|position| !positions.contains(position))
+                .ok_or_else(|| {
+                    TodoError::InvalidInput(format!("Task {path} has no attachment {target}"))
+                })?;
+            positions.push(position);
+        }
+
+        let removed = positions
+            .iter()
+            .map(|&position| attachments[position].clone())
+            .collect();
+        // From the back, so the positions still to remove do not shift
+        positions.sort_unstable_by(|a, b| b.cmp(a));
+        self.replace_with(path, |task| {
+            for position in positions {
+                task.attachments.remove(position);
+            }
+        })?;
+        Ok(removed)
+    }
+
+    /// Moves a completed top-level task with its subtasks into the archive
+    /// of the current list.
+    pub fn archive(&mut self, path: &TaskPath) -> Result<(), TodoError> {
+        let task = self.task(path)?.clone();
+
+        if path.parent().is_some() {
+            return Err(TodoError::InvalidInput(format!(
+                "Task {path} is a subtask, only top-level tasks can be archived"
+            )));
+        }
+        if !task.is_completed() {
+            return Err(TodoError::InvalidInput(format!(
+                "Task {path} is not done yet"
+            )));
+        }
+
+        self.apply(Operation::Archive {
+            list: self.current.clone(),
+            path: path.clone(),
+            position: self.archived().len(),
+            task,
+        })
+    }
+
+    /// Archives the completed top-level tasks of the current list, or of
+    /// every list, as a single undoable change. With `days`, only the tasks
+    /// completed at least that many days ago. Returns how many were archived.
+    pub fn archive_completed(&mut self, all: bool, days: Option<u64>) -> Result<usize, TodoError> {
+        let operations = self.archive_operations(all, days, &[]);
+        let count = operations.len();
+        if count > 0 {
+            self.apply(Operation::Batch { operations })?;
+        }
+        Ok(count)
+    }
+
+    /// Archives tasks once they have been done for `days` days, with every
+    /// change recorded from now on. Undo and redo do not archive.
+    pub fn set_archive_after(&mut self, days: Option<u64>) {
+        self.archive_after = days;
+    }
+
+    /// The archive operations for `archive_completed`, each applying to the
+    /// lists as left by the one before. Tasks with an id in `keep` stay.
+    fn archive_operations(&self, all: bool, days: Option<u64>, keep: &[String]) -> Vec<Operation> {
+        let now = Local::now();
+        let mut operations = Vec::new();
+
+        for list in self
+            .lists
+            .iter()
+            .filter(|list| all || list.name == self.current)
+        {
+            let mut archived = 0;
+            for (position, task) in list.tasks.iter().enumerate() {
+                let done = match days {
+                    Some(days) => archive::completed_before(task, days, now),
+                    None => task.is_completed(),
+                } && !keep.contains(&task.id);
+                if done {
+                    // Earlier tasks are already gone from the list
+                    operations.push(Operation::Archive {
+                        list: list.name.clone(),
+                        path: TaskPath::top(position - archived),
+                        position: list.archive.len() + archived,
+                        task: task.clone(),
+                    });
+                    archived += 1;
+                }
+            }
+        }
+        operations
+    }
+
+    /// The list and archive position of an archived task, picked by index
+    /// in the archive of the current list, or by id in any archive.
+    pub fn locate_archived(&self, task: &TaskRef) -> Result<(String, usize), TodoError> {
+        match task {
+            TaskRef::Path(path)
+                if path.parent().is_none() && path.last() < self.archived().len() =>
+            {
+                Ok((self.current.clone(), path.last()))
+            }
+            TaskRef::Path(path) => Err(TodoError::InvalidIndex(path.clone())),
+            TaskRef::Id(prefix) => {
+                let found: Vec<(String, usize)> = self
+                    .lists
+                    .iter()
+                    .flat_map(|list| {
+                        list.archive
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, task)| {
+                                !task.id.is_empty() && task.id.starts_with(prefix.as_str())
+                            })
+                            .map(|(position, _)| (list.name.clone(), position))
+                    })
+                    .collect();
+
+                match found.as_slice() {
+                    [found] => Ok(found.clone()),
+                    [] => Err(TodoError::UnknownId(prefix.clone())),
+                    _ => Err(TodoError::InvalidInput(format!(
+                        "Several archived tasks have an id starting with #{prefix}, please give more of it"
+                    ))),
+                }
+            }
+        }
+    }
+
+    /// Moves an archived task back to the end of its list, which becomes the
+    /// one worked on. Returns its path there.
+    pub fn restore(&mut self, task: &TaskRef) -> Result<TaskPath, TodoError> {
+        let (list, position) = self.locate_archived(task)?;
+        self.select(&list)?;
+
+        let path = TaskPath::top(self.items().len());
+        self.apply(Operation::Restore {
+            list,
+            position,
+            path: path.clone(),
+            task: self.archived()[position].clone(),
+        })?;
+
+        Ok(path)
+    }
+
+    /// Archived tasks of the current list, or of every list, that the
+    /// retention rules let go, with their list and archive position.
+    pub fn expired(&self, retention: &Retention, all: bool) -> Vec<(&str, usize, &Task)> {
+        let now = Local::now();
+
+        self.lists
+            .iter()
+            .filter(|list| all || list.name == self.current)
+            .flat_map(|list| {
+                retention
+                    .expired(&list.archive, now)
+                    .into_iter()
+                    .map(|position| (list.name.as_str(), position, &list.archive[position]))
+            })
+            .collect()
+    }
+
+    /// Deletes the archived tasks the retention rules let go, as a single
+    /// change that can still be undone. Returns how many were purged.
+    pub fn purge(&mut self, retention: &Retention, all: bool) -> Result<usize, TodoError> {
+        let mut operations = Vec::new();
+        let mut purged_in = ("", 0);
+
+        for (list, position, task) in self.expired(retention, all) {
+            if purged_in.0 != list {
+                purged_in = (list, 0);
+            }
+            // Earlier tasks are already gone from the archive
+            operations.push(Operation::Purge {
+                list: list.to_string(),
+                position: position - purged_in.1,
+                task: task.clone(),
+            });
+            purged_in.1 += 1;
+        }
+
+        let count = operations.len();
+        if count > 0 {
+            self.apply(Operation::Batch { operations })?;
+        }
+        Ok(count)
+    }
+
+    pub fn edit(&mut self, path: &TaskPath, input: &str) -> Result<(), TodoError> {
+        let attributes = Attributes::parse(input, self.now()).map_err(TodoError::InvalidInput)?;
+
+        self.replace_with(path, |task| task.update(attributes))
+    }
+
+    /// Like `edit`, but `input` is the whole task as `Task::inline` shows it,
+    /// so metadata missing from it is cleared.
+    pub fn replace_inline(&mut self, path: &TaskPath, input: &str) -> Result<(), TodoError> {
+        let attributes = Attributes::parse(input, self.now()).map_err(TodoError::InvalidInput)?;
+
+        self.replace_with(path, |task| task.replace(attributes))
+    }
+
+    /// Changes the description (inline syntax), the note and the state of a
+    /// task as a single undoable change. Everything is checked before
+    /// anything changes, so either all of it is saved or none of it.
+    pub fn update(
+        &mut self,
+        path: &TaskPath,
+        description: Option<&str>,
+        note: Option<&str>,
+        state: Option<&str>,
+        force: bool,
+    ) -> Result<(), TodoError> {
+        let attributes = description
+            .map(|description| Attributes::parse(description, self.now()))
+            .transpose()
+            .map_err(TodoError::InvalidInput)?;
+        let state = state
+            .map(|state| self.workflow.find(state).map(str::to_string))
+            .transpose()
+            .map_err(TodoError::InvalidInput)?;
+
+        let before = self.task(path)?;
+        let mut after = before.clone();
+        if let Some(attributes) = attributes {
+            after.update(attributes);
+        }
+        if let Some(note) = note {
+            after.note = note.trim_end().to_string();
+        }
+
+        let mut next = None;
+        match state.as_deref() {
+            Some(workflow::DONE) if !after.is_completed() => {
+                let blockers = self.blocker_labels(&after);
+                if !force && !blockers.is_empty() {
+                    return Err(TodoError::Blocked(format!(
+                        "Task {path} is blocked by {}",
+                        blockers.join(", ")
+                    )));
+                }
+                next = after.next_occurrence(self.now().date());
+                after.complete();
+            }
+            Some(state) if after.state != state => after.set_state(state),
+            _ => {}
+        }
+
+        let replace = Operation::Replace {
+            list: self.current.clone(),
+            path: path.clone(),
+            before: Box::new(before.clone()),
+            after: Box::new(after),
+        };
+        match next {
+            Some(next) => self.apply(Operation::Batch {
+                operations: vec![
+                    replace,
+                    Operation::Insert {
+                        list: self.current.clone(),
+                        path: path.next_sibling(),
+                        task: next,
+                    },
+                ],
+            }),
+            None => self.apply(replace),
+        }
+    }
+}
+
+/// Ids of the tasks an operation brings back from an archive.
+fn restored_ids(operation: &Operation) -> Vec<String> {
+    match operation {
+        Operation::Restore { task, .. } if !task.id.is_empty() => vec![task.id.clone()],
+        Operation::Batch { operations } => operations.iter().flat_map(restored_ids).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The list, path and a copy of the task with `id`, in any list.
+fn find_by_id(lists: &[TaskList], id: &str) -> Option<(String, TaskPath, Task)> {
+    lists.iter().find_map(|list| {
+        tree::flatten(&list.tasks)
+            .into_iter()
+            .find(|(_, task)| task.id == id)
+            .map(|(path, task)| (list.name.clone(), path, task.clone()))
+    })
+}
+
+/// The operations that apply a bulk action to one task, none if it would not change.
+fn bulk_steps(
+    lists: &[TaskList],
+    list: &str,
+    path: &TaskPath,
+    task: &Task,
+    action: &BulkAction,
+    today: NaiveDate,
+) -> Vec<Operation> {
+    let replace = |change: &dyn Fn(&mut Task)| {
+        let mut after = task.clone();
+        change(&mut after);
+        Operation::Replace {
+            list: list.to_string(),
+            path: path.clone(),
+            before: Box::new(task.clone()),
+            after: Box::new(after),
+        }
+    };
+
+    match action {
+        BulkAction::Complete if !task.is_completed() => {
+            let mut steps = vec![replace(&Task::complete)];
+            if let Some(next) = task.next_occurrence(today) {
+                steps.push(Operation::Insert {
+                    list: list.to_string(),
+                    path: path.next_sibling(),
+                    task: next,
+                });
+            }
+            steps
+        }
+        BulkAction::Reopen if task.is_completed() => vec![replace(&Task::reopen)],
+        BulkAction::Delete => vec![Operation::Remove {
+            list: list.to_string(),
+            path: path.clone(),
+            task: task.clone(),
+        }],
+        BulkAction::Retag { add, remove } => {
+            let mut tags: Vec<String> = task
+                .tags
+                .iter()
+                .filter(|tag| {
+                    !remove
+                        .iter()
+                        .any(|removed| removed.eq_ignore_ascii_case(tag))
+                })
+                .cloned()
+                .collect();
+            for tag in add {
+                if !tags
+                    .iter()
+                    .any(|existing| existing.eq_ignore_ascii_case(tag))
+                {
+                    tags.push(tag.clone());
+                }
+            }
+
+            if tags == task.tags {
+                Vec::new()
+            } else {
+                vec![replace(&|task: &mut Task| task.tags = tags.clone())]
+            }
+        }
+        BulkAction::Prioritize(priority) if task.priority != *priority => {
+            vec![replace(&|task: &mut Task| task.priority = *priority)]
+        }
+        BulkAction::Move(to) if to != list => {
+            let position = lists::position(lists, to)
+                .map(|position| lists[position].tasks.len())
+                .unwrap_or_default();
+            vec![
+                Operation::Remove {
+                    list: list.to_string(),
+                    path: path.clone(),
+                    task: task.clone(),
+                },
+                Operation::Insert {
+                    list: to.clone(),
+                    path: TaskPath::top(position),
+                    task: task.clone(),
+                },
+            ]
+        }
+        _ => Vec::new(),
+    }
+}
@@ -0,0 +1,116 @@
+use chrono::{DateTime, Days, Local};
+
+use crate::task::Task;
+
+/// Which archived tasks `todo purge` keeps. A task is purged once every rule
+/// given lets it go; with no rules, the whole archive is purged.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Retention {
+    /// Keeps tasks completed fewer than this many days ago.
+    pub days: Option<u64>,
+    /// Keeps this many of the tasks archived last in each list.
+    pub keep: Option<usize>,
+}
+
+impl Retention {
+    /// Positions of the tasks in `archive` the rules let go, in order.
+    pub fn expired(&self, archive: &[Task], now: DateTime<Local>) -> Vec<usize> {
+        let kept_from = archive.len().saturating_sub(self.keep.unwrap_or(0));
+
+        archive
+            .iter()
+            .enumerate()
+            .filter(|(position, task)| {
+                *position < kept_from
+                    && self
+                        .days
+                        .is_none_or(|days| completed_before(task, days, now))
+            })
+            .map(|(position, _)| position)
+            .collect()
+    }
+}
+
+/// Whether `task` was completed at least `days` days before `now`. Tasks
+/// completed before completion times were recorded count as old.
+pub fn completed_before(task: &Task, days: u64, now: DateTime<Local>) -> bool {
+    let cutoff = now.checked_sub_days(Days::new(days)).unwrap_or(now);
+
+    task.is_completed()
+        && task
+            .completed_at
+            .is_none_or(|completed| completed <= cutoff)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDateTime, TimeZone};
+
+    use super::*;
+    use crate::task::Attributes;
+
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 10, 18, 9, 30, 0).unwrap()
+    }
+
+    /// A task completed `days` days before `now`, or open without.
+    fn task(days: Option<u64>) -> Task {
+        let mut task = Task::new(Attributes::parse("Task", NaiveDateTime::default()).unwrap());
+        if let Some(days) = days {
+            task.complete();
+            task.completed_at = now().checked_sub_days(Days::new(days));
+        }
+        task
+    }
+
+    #[test]
+    fn completed_at_least_days_ago() {
+        assert!(completed_before(&task(Some(7)), 7, now()));
+        assert!(!completed_before(&task(Some(6)), 7, now()));
+        assert!(!completed_before(&task(None), 0, now()));
+
+        let mut unknown = task(Some(0));
+        unknown.completed_at = None;
+        assert!(completed_before(&unknown, 365, now()));
+    }
+
+    /// Archived 30, 20, 10 and 2 days ago, in that order.
+    fn archive() -> Vec<Task> {
+        [30, 20, 10, 2].map(|days| task(Some(days))).to_vec()
+    }
+
+    #[test]
+    fn without_rules_everything_goes() {
+        assert_eq!(
+            Retention::default().expired(&archive(), now()),
+            [0, 1, 2, 3]
+        );
+        assert!(Retention::default().expired(&[], now()).is_empty());
+    }
+
+    #[test]
+    fn every_rule_has_to_let_a_task_go() {
+        let days = Retention {
+            days: Some(15),
+            keep: None,
+        };
+        let keep = Retention {
+            days: None,
+            keep: Some(3),
+        };
+        let both = Retention {
+            days: Some(5),
+            keep: Some(3),
+        };
+
+        assert_eq!(days.expired(&archive(), now()), [0, 1]);
+        assert_eq!(keep.expired(&archive(), now()), [0]);
+        assert_eq!(both.expired(&archive(), now()), [0]);
+        // Keeping more than there are keeps them all
+        let keep_all = Retention {
+            days: None,
+            keep: Some(10),
+        };
+        assert!(keep_all.expired(&archive(), now()).is_empty());
+    }
+}
@@ -0,0 +1,93 @@
+use colored::*;
+use crossterm::terminal;
+
+use todo_core::workflow::{self, Workflow};
+
+/// Width used when the output is not a terminal, e.g. piped to a file.
+const DEFAULT_WIDTH: usize = 100;
+/// Columns never get narrower than this; the board grows wider instead.
+const MIN_COLUMN_WIDTH: usize = 16;
+const SEPARATOR: &str = " │ ";
+
+/// A task as shown on the board.
+pub struct Card {
+    pub state: String,
+    pub label: String,
+    pub color: Option<Color>,
+}
+
+/// The width of the terminal the board is printed to.
+pub fn width() -> usize {
+    terminal::size()
+        .map(|(columns, _)| columns as usize)
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+/// Lays the cards out in a column per state of the workflow, in order.
+/// Cards in a state the workflow no longer has get a column before `done`.
+pub fn render(workflow: &Workflow, cards: &[Card], width: usize) -> String {
+    let mut states: Vec<&str> = workflow.states().iter().map(String::as_str).collect();
+    for card in cards {
+        if !states.contains(&card.state.as_str()) {
+            states.insert(states.len() - 1, &card.state);
+        }
+    }
+
+    let columns: Vec<Vec<&Card>> = states
+        .iter()
+        .map(|state| cards.iter().filter(|card| card.state == *state).collect())
+        .collect();
+
+    let gaps = SEPARATOR.chars().count() * (states.len() - 1);
+    let column_width = (width.saturating_sub(gaps) / states.len()).max(MIN_COLUMN_WIDTH);
+
+    let mut lines = Vec::new();
+
+    let header: Vec<String> = states
+        .iter()
+        .zip(&columns)
+        .map(|(state, cards)| {
+            let title = fit(
+                &format!("{} ({})", state.to_uppercase(), cards.len()),
+                column_width,
+            );
+            title.bold().to_string()
+        })
+        .collect();
+    lines.push(header.join(SEPARATOR));
+    lines.push(vec!["─".repeat(column_width); states.len()].join("─┼─"));
+
+    let rows = columns.iter().map(Vec::len).max().unwrap_or(0);
+    for row in 0..rows {
+        let cells: Vec<String> = states
+            .iter()
+            .zip(&columns)
+            .map(|(state, cards)| match cards.get(row) {
+                Some(card) => {
+                    let text = fit(&card.label, column_width);
+                    match card.color {
+                        Some(color) => text.color(color).to_string(),
+                        None if *state == workflow::DONE => text.dimmed().to_string(),
+                        None => text,
+                    }
+                }
+                None => " ".repeat(column_width),
+            })
+            .collect();
+        lines.push(cells.join(SEPARATOR).trim_end().to_string());
+    }
+
+    lines.join("\n")
+}
+
+/// Pads `text` to `width` characters, or cuts it short with an ellipsis.
+fn fit(text: &str, width: usize) -> String {
+    let length = text.chars().count();
+
+    if length <= width {
+        format!("{text}{}", " ".repeat(width - length))
+    } else {
+        let cut: String = text.chars().take(width.saturating_sub(1)).collect();
+        format!("{cut}…")
+    }
+}
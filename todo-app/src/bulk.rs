@@ -0,0 +1,252 @@
+use std::str::FromStr;
+
+use crate::{
+    query::Query,
+    task::Priority,
+    tree::{TaskPath, TaskRef},
+    workflow::Workflow,
+};
+
+/// Tasks picked for a bulk action: indices, ranges of siblings and ids
+/// separated by commas, e.g. `1-4,7,2.1-2.3,#3fa9`, or else a filter in the
+/// syntax of `Query`, e.g. `status:done +ops` or `done tag:ops`.
+pub enum Selection {
+    Tasks(Vec<Pick>),
+    Filter(Query),
+}
+
+pub enum Pick {
+    Task(TaskRef),
+    /// Siblings from the first to the last index, both included.
+    Range(TaskPath, TaskPath),
+}
+
+impl FromStr for Pick {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let Some((first, last)) = input.split_once('-') else {
+            // Ids need their `#` here, as words such as `cafe` are filters too
+            return if input.starts_with('#') {
+                input.parse().map(Pick::Task)
+            } else {
+                input.parse().map(|path| Pick::Task(TaskRef::Path(path)))
+            };
+        };
+
+        let (first, last): (TaskPath, TaskPath) = (first.parse()?, last.parse()?);
+        if first.parent() != last.parent() || first.last() > last.last() {
+            return Err(format!(
+                "Invalid range: {input} (expected siblings in order, e.g. 2-5 or 3.1-3.4)"
+            ));
+        }
+
+        Ok(Pick::Range(first, last))
+    }
+}
+
+impl Selection {
+    /// Reads a selection; the states of `workflow` can be named bare in filters.
+    pub fn parse(input: &str, workflow: &Workflow) -> Result<Selection, String> {
+        if input.trim().is_empty() {
+            return Err("Please pick tasks by index, range, id or filter".to_string());
+        }
+
+        let picks: Result<Vec<Pick>, String> = input
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .map(str::parse)
+            .collect();
+
+        // Input that looks like picks is more likely a typo than a filter
+        let trimmed = input.trim_start();
+        let looks_like_picks =
+            trimmed.starts_with(|c: char| c.is_ascii_digit() || c == '#') || input.contains(',');
+
+        match picks {
+            Ok(picks) => Ok(Selection::Tasks(picks)),
+            Err(err) if looks_like_picks => Err(err),
+            Err(_) => Query::parse_selection(input, workflow.states()).map(Selection::Filter),
+        }
+    }
+}
+
+/// The siblings from `first` to `last`, both included.
+pub fn siblings(first: &TaskPath, last: &TaskPath) -> Vec<TaskPath> {
+    let parent = first.parent();
+
+    (first.last()..=last.last())
+        .map(|position| match &parent {
+            Some(parent) => parent.child(position),
+            None => TaskPath::top(position),
+        })
+        .collect()
+}
+
+/// A change made to every selected task as one undoable step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BulkAction {
+    Complete,
+    Reopen,
+    Delete,
+    Retag {
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+    /// Sets the priority, or clears it with none.
+    Prioritize(Option<Priority>),
+    /// Moves the tasks with their subtasks to the end of a list.
+    Move(String),
+}
+
+impl BulkAction {
+    /// Reads tag changes such as `+ops -backend`.
+    pub fn retag(changes: &[String]) -> Result<BulkAction, String> {
+        let mut add = Vec::new();
+        let mut remove = Vec::new();
+
+        for change in changes.iter().flat_map(|change| change.split_whitespace()) {
+            match (change.strip_prefix('+'), change.strip_prefix('-')) {
+                (Some(tag), _) if !tag.is_empty() => add.push(tag.to_string()),
+                (_, Some(tag)) if !tag.is_empty() => remove.push(tag.to_string()),
+                _ => {
+                    return Err(format!(
+                        "Invalid tag change: {change} (expected +tag to add or -tag to remove)"
+                    ))
+                }
+            }
+        }
+
+        if add.is_empty() && remove.is_empty() {
+            return Err("Please give tags to add (+tag) or remove (-tag)".to_string());
+        }
+        Ok(BulkAction::Retag { add, remove })
+    }
+
+    /// Reads a priority such as `high`, or `none` to clear it.
+    pub fn prioritize(input: &str) -> Result<BulkAction, String> {
+        if input.eq_ignore_ascii_case("none") {
+            return Ok(BulkAction::Prioritize(None));
+        }
+
+        Priority::parse(input)
+            .map(|priority| BulkAction::Prioritize(Some(priority)))
+            .ok_or_else(|| {
+                format!("Invalid priority: {input} (expected high, medium, low or none)")
+            })
+    }
+
+    /// What the action does to `count` tasks, e.g. `Move 3 tasks to work`.
+    pub fn describe(&self, count: usize) -> String {
+        let tasks = match count {
+            1 => "1 task".to_string(),
+            count => format!("{count} tasks"),
+        };
+
+        match self {
+            BulkAction::Complete => format!("Complete {tasks}"),
+            BulkAction::Reopen => format!("Reopen {tasks}"),
+            BulkAction::Delete => format!("Delete {tasks} with their subtasks"),
+            BulkAction::Retag { add, remove } => {
+                let changes: Vec<String> = add
+                    .iter()
+                    .map(|tag| format!("+{tag}"))
+                    .chain(remove.iter().map(|tag| format!("-{tag}")))
+                    .collect();
+                format!("Tag {tasks} {}", changes.join(" "))
+            }
+            BulkAction::Prioritize(Some(priority)) => {
+                format!("Set the priority of {tasks} to {priority}")
+            }
+            BulkAction::Prioritize(None) => format!("Clear the priority of {tasks}"),
+            BulkAction::Move(list) => format!("Move {tasks} to {list}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Result<Selection, String> {
+        Selection::parse(input, &Workflow::default())
+    }
+
+    fn picks(input: &str) -> Vec<String> {
+        match parse(input) {
+            Ok(Selection::Tasks(picks)) => picks
+                .iter()
+                .map(|pick| match pick {
+                    Pick::Task(task) => task.to_string(),
+                    Pick::Range(first, last) => format!("{first}-{last}"),
+                })
+                .collect(),
+            _ => panic!("{input} is not a list of picks"),
+        }
+    }
+
+    #[test]
+    fn indices_ranges_and_ids() {
+        assert_eq!(picks("1-4, 7,2.1-2.3"), ["1-4", "7", "2.1-2.3"]);
+        assert_eq!(picks("#3fa9"), ["#3fa9"]);
+    }
+
+    #[test]
+    fn words_are_filters() {
+        assert!(matches!(parse("done tag:ops"), Ok(Selection::Filter(_))));
+        assert!(matches!(
+            parse("status:done +ops"),
+            Ok(Selection::Filter(_))
+        ));
+        // Bare hex words are ids elsewhere, but need `#` here
+        assert!(matches!(parse("cafe"), Ok(Selection::Filter(_))));
+    }
+
+    #[test]
+    fn bad_picks_are_errors() {
+        for input in ["#2", "1 2", "3,#zz", "4-2", "2.1-3.2", "1,,x", " "] {
+            assert!(parse(input).is_err(), "{input} should be refused");
+        }
+    }
+
+    #[test]
+    fn ranges_expand_to_siblings() {
+        let path = |input: &str| input.parse::<TaskPath>().unwrap();
+
+        assert_eq!(
+            siblings(&path("2.1"), &path("2.3")),
+            [path("2.1"), path("2.2"), path("2.3")]
+        );
+        assert_eq!(siblings(&path("4"), &path("4")), [path("4")]);
+    }
+
+    #[test]
+    fn tag_changes() {
+        let changes = ["+ops -backend".to_string(), "+web".to_string()];
+
+        assert_eq!(
+            BulkAction::retag(&changes),
+            Ok(BulkAction::Retag {
+                add: vec!["ops".to_string(), "web".to_string()],
+                remove: vec!["backend".to_string()],
+            })
+        );
+        assert!(BulkAction::retag(&["ops".to_string()]).is_err());
+        assert!(BulkAction::retag(&["+".to_string()]).is_err());
+        assert!(BulkAction::retag(&[]).is_err());
+    }
+
+    #[test]
+    fn priorities() {
+        assert_eq!(
+            BulkAction::prioritize("NONE"),
+            Ok(BulkAction::Prioritize(None))
+        );
+        assert!(BulkAction::prioritize("urgent").is_err());
+        assert_eq!(
+            BulkAction::prioritize("high").unwrap().describe(1),
+            "Set the priority of 1 task to high"
+        );
+    }
+}
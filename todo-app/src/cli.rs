@@ -0,0 +1,824 @@
+use chrono::{Days, NaiveDate};
+use clap::{Parser, Subcommand};
+use colored::*;
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    process::ExitCode,
+    time::Duration,
+};
+
+use crate::{
+    editor, output, passphrase,
+    reminders::{self, NotifierKind},
+    server, ui,
+};
+use todo_core::{
+    archive::Retention,
+    bulk::{BulkAction, Selection},
+    dates::Timezone,
+    formats::Format,
+    query::{Query, SortKey},
+    stats::Stats,
+    storage::{Backend, SyncReport},
+    timelog::Effort,
+    tree::TaskRef,
+    TodoApp, TodoError,
+};
+
+/// The operation failed, e.g. the data file could not be written or there is nothing to undo.
+pub const EXIT_FAILURE: u8 = 1;
+/// Invalid arguments; matches the code clap exits with.
+pub const EXIT_USAGE: u8 = 2;
+/// The given index or id does not point at a task, or the list does not exist.
+pub const EXIT_NOT_FOUND: u8 = 3;
+
+#[derive(Parser)]
+#[command(name = "todo", about = "A simple todo list", version)]
+pub struct Cli {
+    /// Data file to use instead of the default location
+    #[arg(short, long, global = true, env = "TODO_FILE")]
+    pub file: Option<PathBuf>,
+
+    /// Keep tasks in a `json` file, an `sqlite` database or a JSON file committed to `git`; guessed from the file extension if left out
+    #[arg(long, global = true, env = "TODO_STORE")]
+    pub store: Option<Backend>,
+
+    /// List to work on for this run instead of the current one
+    #[arg(short, long, global = true)]
+    pub list: Option<String>,
+
+    /// Timezone due dates are given in, e.g. `Europe/Berlin`; defaults to the system one
+    #[arg(long, global = true, env = "TODO_TZ")]
+    pub timezone: Option<Timezone>,
+
+    /// Archive tasks done for this many days along with every change
+    #[arg(long, global = true, env = "TODO_ARCHIVE_AFTER", value_name = "DAYS")]
+    pub archive_after: Option<u64>,
+
+    /// Runs a single operation and exits; without one the interactive menu starts
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Add a new task, e.g. `todo add "Deploy api due:2026-11-01 +backend !high"`
+    Add {
+        description: String,
+        /// Add the task as a subtask of this one, e.g. `2`, `2.1` or `#3fa9`
+        #[arg(long)]
+        parent: Option<TaskRef>,
+    },
+    /// List tasks, optionally filtered and sorted
+    List {
+        /// Show the tasks of every list
+        #[arg(long)]
+        all: bool,
+        /// Filter terms, e.g. `status:open +ops !high due:week before:2026-11-01 /regex/`
+        #[arg(long)]
+        filter: Option<String>,
+        /// Sort by `due`, `priority` or `created`
+        #[arg(long)]
+        sort: Option<SortKey>,
+    },
+    /// Mark a task (and its subtasks) as done; subtasks are addressed like `2.1`
+    Done {
+        task: TaskRef,
+        /// Complete the task even if it waits for open tasks
+        #[arg(long)]
+        force: bool,
+    },
+    /// Move a task to a state of the workflow, e.g. `todo state 2 review`
+    State {
+        task: TaskRef,
+        state: String,
+        /// Complete the task even if it waits for open tasks
+        #[arg(long)]
+        force: bool,
+    },
+    /// Move a task on to the next state of the workflow
+    Advance {
+        task: TaskRef,
+        /// Complete the task even if it waits for open tasks
+        #[arg(long)]
+        force: bool,
+    },
+    /// Show the tasks in a column per state
+    Board {
+        /// Include every list
+        #[arg(long)]
+        all: bool,
+    },
+    /// Show the states tasks move through, or change the ones between todo and done
+    Workflow {
+        #[command(subcommand)]
+        command: Option<WorkflowCommand>,
+    },
+    /// Make a task wait for other tasks, e.g. `todo depend 3 1 2`; tasks of other lists are picked by id
+    Depend {
+        task: TaskRef,
+        #[arg(required = true)]
+        on: Vec<TaskRef>,
+        /// Remove the dependencies instead
+        #[arg(long)]
+        remove: bool,
+    },
+    /// Show the tasks that can be worked on now
+    Next {
+        /// Include every list
+        #[arg(long)]
+        all: bool,
+    },
+    /// Delete a task and its subtasks
+    Rm { task: TaskRef },
+    /// Change many tasks at once, picked by index, range, id or filter, after a preview
+    Bulk {
+        #[command(subcommand)]
+        command: BulkCommand,
+        /// Apply without asking for confirmation
+        #[arg(short, long, global = true)]
+        yes: bool,
+    },
+    /// Move a completed task, or every completed task, out of the list into its archive
+    Archive {
+        /// Top-level task to archive; every completed one if left out
+        task: Option<TaskRef>,
+        /// Archive the completed tasks of every list
+        #[arg(long, conflicts_with = "task")]
+        all: bool,
+        /// Only archive tasks done for at least this many days
+        #[arg(long, value_name = "DAYS", conflicts_with = "task")]
+        older_than: Option<u64>,
+    },
+    /// Show archived tasks, with the index `todo restore` takes
+    Archived {
+        /// Show the archive of every list
+        #[arg(long)]
+        all: bool,
+        /// Filter terms, as for `todo list`
+        #[arg(long)]
+        filter: Option<String>,
+    },
+    /// Move an archived task back to the end of its list
+    Restore { task: TaskRef },
+    /// Delete archived tasks for good, keeping the ones the rules retain
+    Purge {
+        /// Keep tasks done fewer than this many days ago
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<u64>,
+        /// Keep this many of the tasks archived last
+        #[arg(long, value_name = "COUNT")]
+        keep: Option<usize>,
+        /// Purge the archive of every list
+        #[arg(long)]
+        all: bool,
+        /// Purge without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Show a task with everything about it, its note and attachments included
+    Show { task: TaskRef },
+    /// Write the Markdown note of a task in $EDITOR, or set it with --message
+    Note {
+        task: TaskRef,
+        /// Note to set instead of opening the editor; an empty one removes it
+        #[arg(short, long)]
+        message: Option<String>,
+    },
+    /// Attach files or URLs to a task, e.g. `todo attach 3 spec.pdf https://example.com/issue/42`
+    Attach {
+        task: TaskRef,
+        #[arg(required = true)]
+        targets: Vec<String>,
+        /// Remove the attachments instead, by number, URL or path
+        #[arg(long)]
+        remove: bool,
+    },
+    /// Change the description of a task
+    Edit { task: TaskRef, description: String },
+    /// Move a task and its subtasks to the end of another list
+    Mv {
+        task: TaskRef,
+        #[arg(value_name = "LIST")]
+        to: String,
+    },
+    /// Show the lists, or create, rename, switch or delete one
+    Lists {
+        #[command(subcommand)]
+        command: Option<ListsCommand>,
+    },
+    /// Start the timer of a task, stopping any other running timer
+    Start { task: TaskRef },
+    /// Stop the timer of a task, or every running timer
+    Stop { task: Option<TaskRef> },
+    /// Log time spent on a task without the timer, e.g. `todo log 2 1h30m`
+    Log {
+        task: TaskRef,
+        duration: Effort,
+        /// Day the work was done on; defaults to ending now
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+    /// Summarize time spent per task, tag and day
+    Report {
+        /// First day to include; defaults to six days before `--to`
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        /// Last day to include; defaults to today
+        #[arg(long)]
+        to: Option<NaiveDate>,
+    },
+    /// Show completions per day and week, time to complete, overdue tasks and counts per tag
+    Stats {
+        /// First day to include; defaults to four weeks before `--to`
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        /// Last day to include; defaults to today
+        #[arg(long)]
+        to: Option<NaiveDate>,
+        /// Include every list
+        #[arg(long)]
+        all: bool,
+        /// Print the numbers as JSON instead of charts
+        #[arg(long)]
+        json: bool,
+    },
+    /// Keep running and remind of tasks as they come due
+    Watch {
+        /// How long before the due time to remind, e.g. `15m` or `1h`
+        #[arg(long, env = "TODO_LEAD", default_value = "15m")]
+        lead: Effort,
+        /// Seconds between checks of the data file
+        #[arg(long, default_value_t = 30)]
+        interval: u64,
+        /// Where to send reminders: `stdout`, `bell` and/or `desktop`
+        #[arg(long, value_delimiter = ',', default_value = "stdout,bell")]
+        notify: Vec<NotifierKind>,
+        /// Check once and exit, e.g. from cron
+        #[arg(long)]
+        once: bool,
+    },
+    /// Hold back reminders for a task, e.g. `todo snooze 2 1h`
+    Snooze {
+        task: TaskRef,
+        #[arg(default_value = reminders::DEFAULT_SNOOZE)]
+        duration: Effort,
+    },
+    /// Merge in the changes teammates pushed and push yours (needs `--store git`)
+    Sync {
+        /// Git remote to pull from and push to
+        #[arg(long, default_value = "origin")]
+        remote: String,
+    },
+    /// Revert the last change
+    Undo,
+    /// Reapply the last undone change
+    Redo,
+    /// Open the full-screen interface
+    Tui,
+    /// Encrypt the data file with a passphrase, asked for on every start (json store only)
+    Encrypt,
+    /// Change the passphrase of an encrypted data file
+    Rekey,
+    /// Store the data file unencrypted again
+    Decrypt,
+    /// Serve the tasks as a JSON API on localhost, for editor plugins and web pages
+    Serve {
+        /// Port to listen on
+        #[arg(long, default_value_t = server::DEFAULT_PORT)]
+        port: u16,
+        /// Let web pages from this origin use the API, e.g. `http://localhost:3000`
+        #[arg(long)]
+        allow_origin: Option<String>,
+    },
+    /// Export the tasks of the current list as todo.txt, Markdown or CSV
+    Export {
+        /// `todotxt`, `markdown` or `csv`; guessed from the output file extension if left out
+        #[arg(long)]
+        format: Option<Format>,
+        /// File to write to instead of standard output
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Import tasks from a todo.txt, Markdown or CSV file and append them to the list
+    Import {
+        input: PathBuf,
+        /// `todotxt`, `markdown` or `csv`; guessed from the file extension if left out
+        #[arg(long)]
+        format: Option<Format>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ListsCommand {
+    /// Create an empty list
+    New { name: String },
+    /// Rename a list
+    Rename { from: String, to: String },
+    /// Make a list the current one for later runs
+    Switch { name: String },
+    /// Delete a list and all its tasks
+    Rm { name: String },
+}
+
+/// Every bulk action takes the tasks to change first: indices, ranges and
+/// ids such as `1-4,7,#3fa9`, or a filter such as `"status:done +ops"`.
+#[derive(Subcommand)]
+pub enum BulkCommand {
+    /// Mark the tasks as done
+    Done {
+        selection: String,
+        /// Complete tasks even if they wait for open tasks
+        #[arg(long)]
+        force: bool,
+    },
+    /// Reopen completed tasks
+    Reopen { selection: String },
+    /// Delete the tasks and their subtasks
+    Rm { selection: String },
+    /// Add and remove tags, e.g. `todo bulk tag 1-3 +ops -backend`
+    Tag {
+        selection: String,
+        #[arg(required = true, allow_hyphen_values = true)]
+        changes: Vec<String>,
+    },
+    /// Set the priority to `high`, `medium` or `low`, or clear it with `none`
+    Priority { selection: String, priority: String },
+    /// Move the tasks and their subtasks to the end of another list
+    Mv {
+        selection: String,
+        #[arg(value_name = "LIST")]
+        to: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum WorkflowCommand {
+    /// Set the states between todo and done, e.g. `todo workflow set in-progress review`
+    Set { states: Vec<String> },
+}
+
+pub fn run(mut todo_app: TodoApp, command: Command) -> ExitCode {
+    let result = match command {
+        Command::Add {
+            description,
+            parent,
+        } => parent
+            .map(|parent| todo_app.resolve(&parent))
+            .transpose()
+            .and_then(|parent| todo_app.add(&description, parent.as_ref()))
+            .map(|_| ()),
+        Command::List { all, filter, sort } => {
+            Query::parse(filter.as_deref().unwrap_or_default(), sort)
+                .map(|query| {
+                    if all {
+                        output::list_all(&todo_app, &query)
+                    } else {
+                        output::list_matching(&todo_app, &query)
+                    }
+                })
+                .map_err(TodoError::InvalidInput)
+        }
+        Command::Done { task, force } => todo_app
+            .resolve(&task)
+            .and_then(|path| todo_app.complete(&path, force)),
+        Command::State { task, state, force } => todo_app
+            .resolve(&task)
+            .and_then(|path| todo_app.set_state(&path, &state, force)),
+        Command::Advance { task, force } => todo_app.resolve(&task).and_then(|path| {
+            todo_app
+                .advance(&path, force)
+                .map(|state| println!("Moved task {path} to {state}"))
+        }),
+        Command::Board { all } => {
+            output::print_board(&todo_app, all);
+            Ok(())
+        }
+        Command::Workflow { command } => workflow(&mut todo_app, command),
+        Command::Depend { task, on, remove } => todo_app.resolve(&task).and_then(|path| {
+            if remove {
+                todo_app.remove_dependencies(&path, &on)
+            } else {
+                todo_app.add_dependencies(&path, &on)
+            }
+        }),
+        Command::Next { all } => {
+            output::list_actionable(&todo_app, all);
+            Ok(())
+        }
+        Command::Rm { task } => todo_app
+            .resolve(&task)
+            .and_then(|path| todo_app.remove(&path))
+            .map(|task| println!("Deleted task: {}", task.description)),
+        Command::Bulk { command, yes } => bulk(&mut todo_app, command, yes),
+        Command::Archive {
+            task: Some(task), ..
+        } => todo_app
+            .resolve(&task)
+            .and_then(|path| todo_app.archive(&path))
+            .map(|_| println!("Archived task {task}")),
+        Command::Archive {
+            task: None,
+            all,
+            older_than,
+        } => todo_app
+            .archive_completed(all, older_than)
+            .map(|count| match count {
+                0 => println!("No completed tasks to archive"),
+                count => println!("Archived {count} task(s)"),
+            }),
+        Command::Archived { all, filter } => {
+            Query::parse(filter.as_deref().unwrap_or_default(), None)
+                .map(|query| output::list_archived(&todo_app, all, &query))
+                .map_err(TodoError::InvalidInput)
+        }
+        Command::Restore { task } => todo_app
+            .restore(&task)
+            .map(|path| println!("Restored task to {} as {path}", todo_app.current())),
+        Command::Purge {
+            older_than,
+            keep,
+            all,
+            yes,
+        } => purge(
+            &mut todo_app,
+            &Retention {
+                days: older_than,
+                keep,
+            },
+            all,
+            yes,
+        ),
+        Command::Show { task } => todo_app.resolve(&task).and_then(|path| {
+            let task = todo_app.task(&path)?;
+            output::print_details(&todo_app, &path, task);
+            Ok(())
+        }),
+        Command::Note { task, message } => note(&mut todo_app, &task, message),
+        Command::Attach {
+            task,
+            targets,
+            remove,
+        } => todo_app.resolve(&task).and_then(|path| {
+            if remove {
+                for removed in todo_app.detach(&path, &targets)? {
+                    println!("Removed {removed} from task {task}");
+                }
+            } else {
+                for attached in todo_app.attach(&path, &targets)? {
+                    println!("Attached {attached} to task {task}");
+                }
+            }
+            Ok(())
+        }),
+        Command::Edit { task, description } => todo_app
+            .resolve(&task)
+            .and_then(|path| todo_app.edit(&path, &description)),
+        Command::Mv { task, to } => todo_app
+            .resolve(&task)
+            .and_then(|path| todo_app.move_task(&path, &to))
+            .map(|path| println!("Moved task to {to} as {path}")),
+        Command::Lists { command } => lists(&mut todo_app, command),
+        Command::Start { task } => todo_app
+            .resolve(&task)
+            .and_then(|path| todo_app.start_timer(&path))
+            .map(|_| println!("Started timer of task {task}")),
+        Command::Stop { task } => task
+            .map(|task| todo_app.resolve(&task))
+            .transpose()
+            .and_then(|path| todo_app.stop_timer(path.as_ref()))
+            .map(|count| println!("Stopped {count} timer(s)")),
+        Command::Log {
+            task,
+            duration,
+            date,
+        } => todo_app
+            .resolve(&task)
+            .and_then(|path| todo_app.log_work(&path, duration, date))
+            .map(|_| println!("Logged {duration} on task {task}")),
+        Command::Report { from, to } => report(&todo_app, from, to),
+        Command::Stats {
+            from,
+            to,
+            all,
+            json,
+        } => stats(&todo_app, from, to, all, json),
+        Command::Watch {
+            lead,
+            interval,
+            notify,
+            once,
+        } => {
+            let mut notifiers: Vec<_> = notify.iter().map(NotifierKind::build).collect();
+            reminders::watch(
+                &mut todo_app,
+                &mut notifiers,
+                lead.delta(),
+                Duration::from_secs(interval.max(1)),
+                once,
+            )
+        }
+        Command::Snooze { task, duration } => todo_app
+            .resolve(&task)
+            .and_then(|path| todo_app.snooze(&path, duration))
+            .map(|_| println!("Snoozed task {task} for {duration}")),
+        Command::Sync { remote } => todo_app.sync(&remote).map(|report| print_sync(&report)),
+        Command::Undo => todo_app
+            .undo()
+            .map(|operation| println!("Undid {}", operation.describe())),
+        Command::Redo => todo_app
+            .redo()
+            .map(|operation| println!("Redid {}", operation.describe())),
+        Command::Export { format, output } => export(&todo_app, format, output),
+        Command::Import { input, format } => import(&mut todo_app, input, format),
+        Command::Encrypt => encrypt(&mut todo_app, false),
+        Command::Rekey => encrypt(&mut todo_app, true),
+        Command::Decrypt => decrypt(&mut todo_app),
+        Command::Serve { port, allow_origin } => {
+            server::serve(&mut todo_app, port, allow_origin.as_deref())
+        }
+        Command::Tui => {
+            return match ui::run(todo_app) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(err) => {
+                    eprintln!("{}", err.to_string().red());
+                    ExitCode::from(EXIT_FAILURE)
+                }
+            };
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err.to_string().red());
+
+            match err {
+                TodoError::InvalidIndex(_)
+                | TodoError::UnknownId(_)
+                | TodoError::UnknownList(_) => ExitCode::from(EXIT_NOT_FOUND),
+                TodoError::InvalidInput(_) => ExitCode::from(EXIT_USAGE),
+                TodoError::Blocked(_)
+                | TodoError::History(_)
+                | TodoError::Io(_)
+                | TodoError::Storage(_)
+                | TodoError::Sync(_) => ExitCode::from(EXIT_FAILURE),
+            }
+        }
+    }
+}
+
+fn lists(todo_app: &mut TodoApp, command: Option<ListsCommand>) -> Result<(), TodoError> {
+    match command {
+        None => {
+            output::show_lists(todo_app);
+            Ok(())
+        }
+        Some(ListsCommand::New { name }) => todo_app
+            .create_list(&name)
+            .map(|_| println!("Created list {name}")),
+        Some(ListsCommand::Rename { from, to }) => todo_app
+            .rename_list(&from, &to)
+            .map(|_| println!("Renamed list {from} to {to}")),
+        Some(ListsCommand::Switch { name }) => todo_app
+            .switch_list(&name)
+            .map(|_| println!("Switched to list {name}")),
+        Some(ListsCommand::Rm { name }) => todo_app.delete_list(&name).map(|list| {
+            println!("Deleted list {} with {} tasks", list.name, list.tasks.len());
+        }),
+    }
+}
+
+fn workflow(todo_app: &mut TodoApp, command: Option<WorkflowCommand>) -> Result<(), TodoError> {
+    match command {
+        None => {
+            output::show_workflow(todo_app);
+            Ok(())
+        }
+        Some(WorkflowCommand::Set { states }) => todo_app.set_workflow(&states).map(|_| {
+            println!("Workflow is now:");
+            output::show_workflow(todo_app);
+        }),
+    }
+}
+
+/// Encrypts the data file, or with `rekey` changes the passphrase of an encrypted one.
+fn encrypt(todo_app: &mut TodoApp, rekey: bool) -> Result<(), TodoError> {
+    match (todo_app.is_encrypted()?, rekey) {
+        (true, false) => Err(TodoError::InvalidInput(
+            "The data file is encrypted already, change its passphrase with `todo rekey`"
+                .to_string(),
+        )),
+        (false, true) => Err(TodoError::InvalidInput(
+            "The data file is not encrypted, encrypt it with `todo encrypt`".to_string(),
+        )),
+        _ => {
+            let passphrase = passphrase::read_new()?;
+            todo_app.encrypt(&passphrase)?;
+            if rekey {
+                println!("Changed the passphrase");
+            } else {
+                println!("Encrypted the data file, keep the passphrase safe: the tasks cannot be recovered without it");
+            }
+            Ok(())
+        }
+    }
+}
+
+fn decrypt(todo_app: &mut TodoApp) -> Result<(), TodoError> {
+    if !todo_app.is_encrypted()? {
+        return Err(TodoError::InvalidInput(
+            "The data file is not encrypted".to_string(),
+        ));
+    }
+
+    todo_app.decrypt()?;
+    println!("Decrypted the data file");
+    Ok(())
+}
+
+fn bulk(todo_app: &mut TodoApp, command: BulkCommand, yes: bool) -> Result<(), TodoError> {
+    let (selection, action, force) = match command {
+        BulkCommand::Done { selection, force } => (selection, Ok(BulkAction::Complete), force),
+        BulkCommand::Reopen { selection } => (selection, Ok(BulkAction::Reopen), false),
+        BulkCommand::Rm { selection } => (selection, Ok(BulkAction::Delete), false),
+        BulkCommand::Tag { selection, changes } => (selection, BulkAction::retag(&changes), false),
+        BulkCommand::Priority {
+            selection,
+            priority,
+        } => (selection, BulkAction::prioritize(&priority), false),
+        BulkCommand::Mv { selection, to } => (selection, Ok(BulkAction::Move(to)), false),
+    };
+    let action = action.map_err(TodoError::InvalidInput)?;
+    let selection =
+        Selection::parse(&selection, todo_app.workflow()).map_err(TodoError::InvalidInput)?;
+
+    let picked = todo_app.pick(&selection)?;
+    if picked.is_empty() {
+        println!("No tasks match");
+        return Ok(());
+    }
+    output::print_preview(todo_app, &picked, &action);
+    let ids: Vec<String> = picked.iter().map(|entry| entry.task.id.clone()).collect();
+
+    if !yes && !confirm() {
+        println!("Nothing was changed");
+        return Ok(());
+    }
+
+    let count = todo_app.bulk(&ids, &action, force)?;
+    println!("Changed {count} task(s)");
+    Ok(())
+}
+
+fn note(todo_app: &mut TodoApp, task: &TaskRef, message: Option<String>) -> Result<(), TodoError> {
+    let path = todo_app.resolve(task)?;
+    let note = match message {
+        Some(message) => message,
+        None => editor::edit(&todo_app.task(&path)?.note)?,
+    };
+
+    if todo_app.set_note(&path, &note)? {
+        println!("Saved the note of task {task}");
+    } else {
+        println!("The note of task {task} did not change");
+    }
+    Ok(())
+}
+
+fn purge(
+    todo_app: &mut TodoApp,
+    retention: &Retention,
+    all: bool,
+    yes: bool,
+) -> Result<(), TodoError> {
+    let expired = todo_app.expired(retention, all);
+    if expired.is_empty() {
+        println!("No archived tasks to purge");
+        return Ok(());
+    }
+    output::print_expired(todo_app, &expired);
+
+    if !yes && !confirm() {
+        println!("Nothing was changed");
+        return Ok(());
+    }
+
+    let count = todo_app.purge(retention, all)?;
+    println!("Purged {count} task(s)");
+    Ok(())
+}
+
+/// Asks whether to go ahead; anything but yes, or no answer at all, is no.
+fn confirm() -> bool {
+    print!("Apply? (y/N) ");
+    let _ = io::stdout().flush();
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok()
+        && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+fn print_sync(report: &SyncReport) {
+    for description in &report.conflicts {
+        println!(
+            "{}",
+            format!("\"{description}\" was changed on both sides, kept your changes").yellow()
+        );
+    }
+
+    match (report.pulled, report.pushed) {
+        (false, false) => println!("Already up to date with {}", report.upstream),
+        (true, false) => println!("Pulled changes from {}", report.upstream),
+        (false, true) => println!("Pushed changes to {}", report.upstream),
+        (true, true) => println!("Merged and pushed changes with {}", report.upstream),
+    }
+}
+
+fn report(
+    todo_app: &TodoApp,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<(), TodoError> {
+    let to = to.unwrap_or_else(|| todo_app.now().date());
+    let from = from.unwrap_or_else(|| to - Days::new(6));
+
+    if from > to {
+        return Err(TodoError::InvalidInput(format!(
+            "--from {from} is after --to {to}"
+        )));
+    }
+
+    output::print_report(todo_app, from, to);
+    Ok(())
+}
+
+fn stats(
+    todo_app: &TodoApp,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    all: bool,
+    json: bool,
+) -> Result<(), TodoError> {
+    let to = to.unwrap_or_else(|| todo_app.now().date());
+    let from = from.unwrap_or_else(|| Stats::default_from(to));
+
+    if from > to {
+        return Err(TodoError::InvalidInput(format!(
+            "--from {from} is after --to {to}"
+        )));
+    }
+
+    let stats = todo_app.stats(from, to, all);
+    if json {
+        let json = serde_json::to_string_pretty(&stats)
+            .map_err(|err| TodoError::InvalidInput(err.to_string()))?;
+        println!("{json}");
+    } else {
+        output::print_stats(&stats);
+    }
+
+    Ok(())
+}
+
+fn resolve_format(format: Option<Format>, path: Option<&PathBuf>) -> Result<Format, TodoError> {
+    format
+        .or_else(|| path.and_then(|path| Format::from_path(path)))
+        .ok_or_else(|| {
+            TodoError::InvalidInput("Please pass --format todotxt, markdown or csv".to_string())
+        })
+}
+
+fn export(
+    todo_app: &TodoApp,
+    format: Option<Format>,
+    output: Option<PathBuf>,
+) -> Result<(), TodoError> {
+    let format = resolve_format(format, output.as_ref())?;
+    let content = format
+        .export(todo_app.items())
+        .map_err(TodoError::InvalidInput)?;
+
+    match output {
+        Some(path) => fs::write(path, content).map_err(TodoError::Io),
+        None => {
+            print!("{content}");
+            Ok(())
+        }
+    }
+}
+
+fn import(todo_app: &mut TodoApp, input: PathBuf, format: Option<Format>) -> Result<(), TodoError> {
+    let format = resolve_format(format, Some(&input))?;
+    let content = fs::read_to_string(&input).map_err(TodoError::Io)?;
+    let tasks = format
+        .import(&content, todo_app.now())
+        .map_err(TodoError::InvalidInput)?;
+
+    let count = todo_app.import(tasks)?;
+    println!("Imported {count} tasks from {}", input.display());
+
+    Ok(())
+}
@@ -0,0 +1,334 @@
+use chrono::{
+    DateTime, Datelike, Days, Local, Month, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta,
+    TimeZone, Timelike, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use std::str::FromStr;
+
+use crate::{recurrence::last_day_of_month, task::Due};
+
+/// Longest expression read after `due:`, e.g. `in 3 days at 5:30 pm`.
+pub const MAX_WORDS: usize = 6;
+
+/// The timezone due dates are entered and compared in. Due dates are stored
+/// as wall-clock times, so this decides when "tomorrow" starts and when a
+/// task becomes overdue.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Timezone {
+    /// The timezone of the system.
+    #[default]
+    Local,
+    Named(Tz),
+}
+
+impl Timezone {
+    /// The wall-clock time at `instant`.
+    pub fn at<T: TimeZone>(&self, instant: DateTime<T>) -> NaiveDateTime {
+        match self {
+            Timezone::Local => instant.with_timezone(&Local).naive_local(),
+            Timezone::Named(tz) => instant.with_timezone(tz).naive_local(),
+        }
+    }
+
+    pub fn now(&self) -> NaiveDateTime {
+        self.at(Utc::now())
+    }
+}
+
+impl FromStr for Timezone {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        if input.eq_ignore_ascii_case("local") {
+            return Ok(Timezone::Local);
+        }
+
+        input.parse().map(Timezone::Named).map_err(|_| {
+            format!("Unknown timezone: {input} (expected a name such as Europe/Berlin, or local)")
+        })
+    }
+}
+
+/// Reads a due date relative to `now`: ISO dates such as `2026-11-01` or
+/// `2026-11-01T17:00`, or expressions such as `tomorrow 5pm`,
+/// `next friday`, `in 3 days`, `in 2 hours`, `nov 3` and `end of month`.
+pub fn parse_due(input: &str, now: NaiveDateTime) -> Option<Due> {
+    if let Some(due) = Due::parse(input.trim()) {
+        return Some(due);
+    }
+
+    let input = input.to_lowercase();
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let today = now.date();
+
+    if let ["in", amount, unit] = words.as_slice() {
+        if let Some(due) = parse_offset(amount, unit, now) {
+            return Some(due);
+        }
+    }
+
+    // A time alone is the next time the clock shows it
+    if let Some(time) = parse_time(&words) {
+        let date = if time > now.time() {
+            today
+        } else {
+            today + Days::new(1)
+        };
+        return Some(Due {
+            date,
+            time: Some(time),
+        });
+    }
+
+    // The longest leading words that form a date, then an optional time
+    (1..=words.len()).rev().find_map(|split| {
+        let date = parse_date(&words[..split], today)?;
+        let rest = &words[split..];
+        let time = if rest.is_empty() {
+            None
+        } else {
+            Some(parse_time(rest)?)
+        };
+
+        Some(Due { date, time })
+    })
+}
+
+/// Reads the due date after `due:` in inline syntax, which can run over
+/// several words as in `due:next friday`. Returns the date and how many of
+/// the `following` words it took, taking as many as still form a date.
+pub fn parse_due_words(
+    first: &str,
+    following: &[&str],
+    now: NaiveDateTime,
+) -> Option<(Due, usize)> {
+    if first.is_empty() {
+        return None;
+    }
+
+    let most = following.len().min(MAX_WORDS - 1);
+    (0..=most).rev().find_map(|taken| {
+        let mut expression = first.to_string();
+        for word in &following[..taken] {
+            expression.push(' ');
+            expression.push_str(word);
+        }
+
+        parse_due(&expression, now).map(|due| (due, taken))
+    })
+}
+
+fn parse_date(words: &[&str], today: NaiveDate) -> Option<NaiveDate> {
+    match words {
+        ["today"] => Some(today),
+        ["tomorrow"] => today.checked_add_days(Days::new(1)),
+        ["next", "week"] => Some(next_weekday(today, Weekday::Mon)),
+        ["next", "month"] => today.with_day(1)?.checked_add_months(Months::new(1)),
+        ["next", "year"] => NaiveDate::from_ymd_opt(today.year() + 1, 1, 1),
+        ["end", "of", "week"] => Some(today.week(Weekday::Mon).last_day()),
+        ["end", "of", "month"] => Some(last_day_of_month(today)),
+        ["end", "of", "year"] => NaiveDate::from_ymd_opt(today.year(), 12, 31),
+        ["in", amount, unit] => {
+            let amount = parse_amount(amount)?;
+            match unit.trim_end_matches('s') {
+                "day" => today.checked_add_days(Days::new(amount as u64)),
+                "week" => today.checked_add_days(Days::new(7 * amount as u64)),
+                "month" => today.checked_add_months(Months::new(amount)),
+                _ => None,
+            }
+        }
+        [day] | ["next", day] if day.parse::<Weekday>().is_ok() => {
+            Some(next_weekday(today, day.parse().ok()?))
+        }
+        [month, day] => {
+            parse_day_of_month(month, day, today).or_else(|| parse_day_of_month(day, month, today))
+        }
+        [date] => NaiveDate::parse_from_str(date, "%Y-%m-%d").ok(),
+        _ => None,
+    }
+}
+
+/// `in 2 hours` or `in 30 minutes`, which give a time as well as a date.
+fn parse_offset(amount: &str, unit: &str, now: NaiveDateTime) -> Option<Due> {
+    let amount = parse_amount(amount)? as i64;
+    let delta = match unit {
+        "hour" | "hours" | "h" => TimeDelta::try_hours(amount)?,
+        "minute" | "minutes" | "min" | "mins" => TimeDelta::try_minutes(amount)?,
+        _ => return None,
+    };
+    let at = now.checked_add_signed(delta)?;
+
+    Some(Due {
+        date: at.date(),
+        time: NaiveTime::from_hms_opt(at.hour(), at.minute(), 0),
+    })
+}
+
+fn parse_amount(amount: &str) -> Option<u32> {
+    match amount {
+        "a" | "an" | "one" => Some(1),
+        _ => amount.parse().ok().filter(|amount| *amount > 0),
+    }
+}
+
+/// `5pm`, `5:30 pm`, `at 17:00` or `noon`.
+fn parse_time(words: &[&str]) -> Option<NaiveTime> {
+    let words = match words {
+        ["at", rest @ ..] => rest,
+        _ => words,
+    };
+    if words.is_empty() || words.len() > 2 {
+        return None;
+    }
+
+    let time = words.concat();
+    if time == "noon" {
+        return NaiveTime::from_hms_opt(12, 0, 0);
+    }
+
+    let (clock, offset) = if let Some(clock) = time.strip_suffix("am") {
+        (clock, Some(0))
+    } else if let Some(clock) = time.strip_suffix("pm") {
+        (clock, Some(12))
+    } else {
+        (time.as_str(), None)
+    };
+
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (hour.parse().ok()?, minute.parse().ok()?),
+        // A bare number is only a time with am or pm, e.g. `5pm`
+        None if offset.is_some() => (clock.parse().ok()?, 0),
+        _ => return None,
+    };
+
+    let hour = match offset {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(offset) => hour % 12 + offset,
+        None => hour,
+    };
+
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// `nov 3` or `3rd november`, this year or next if it has passed.
+fn parse_day_of_month(month: &str, day: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let month = Month::from_str(month).ok()?.number_from_month();
+    let day: u32 = day
+        .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+        .parse()
+        .ok()?;
+
+    let date = NaiveDate::from_ymd_opt(today.year(), month, day)?;
+    if date >= today {
+        Some(date)
+    } else {
+        NaiveDate::from_ymd_opt(today.year() + 1, month, day)
+    }
+}
+
+/// The first `weekday` after `today`, a week ahead if today is one.
+fn next_weekday(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let ahead = (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+    let ahead = if ahead == 0 { 7 } else { ahead };
+
+    today + Days::new(ahead as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn at(date: NaiveDate, hour: u32, minute: u32) -> NaiveDateTime {
+        date.and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    /// Wednesday morning.
+    fn now() -> NaiveDateTime {
+        at(date(2026, 10, 14), 9, 30)
+    }
+
+    fn day(date: NaiveDate) -> Due {
+        Due { date, time: None }
+    }
+
+    #[test]
+    fn tomorrow_at_a_time() {
+        let expected = Due {
+            date: date(2026, 10, 15),
+            time: NaiveTime::from_hms_opt(17, 0, 0),
+        };
+
+        assert_eq!(parse_due("tomorrow 5pm", now()), Some(expected));
+        assert_eq!(parse_due("Tomorrow at 17:00", now()), Some(expected));
+    }
+
+    #[test]
+    fn next_weekday_is_always_ahead() {
+        assert_eq!(
+            parse_due("next friday", now()),
+            Some(day(date(2026, 10, 16)))
+        );
+
+        // On a Friday, the Friday a week later
+        let friday = at(date(2026, 10, 16), 9, 30);
+        assert_eq!(
+            parse_due("next friday", friday),
+            Some(day(date(2026, 10, 23)))
+        );
+        assert_eq!(parse_due("friday", friday), Some(day(date(2026, 10, 23))));
+    }
+
+    #[test]
+    fn days_from_now() {
+        assert_eq!(parse_due("in 3 days", now()), Some(day(date(2026, 10, 17))));
+        assert_eq!(parse_due("in a week", now()), Some(day(date(2026, 10, 21))));
+    }
+
+    #[test]
+    fn end_of_month() {
+        let on = |date| parse_due("end of month", at(date, 12, 0));
+
+        assert_eq!(on(date(2027, 1, 31)), Some(day(date(2027, 1, 31))));
+        assert_eq!(on(date(2027, 2, 10)), Some(day(date(2027, 2, 28))));
+        // Leap year
+        assert_eq!(on(date(2028, 2, 10)), Some(day(date(2028, 2, 29))));
+    }
+
+    #[test]
+    fn named_timezone_decides_the_day() {
+        let instant = Utc.with_ymd_and_hms(2026, 10, 16, 2, 0, 0).unwrap();
+        let new_york: Timezone = "America/New_York".parse().unwrap();
+        let tokyo: Timezone = "Asia/Tokyo".parse().unwrap();
+
+        assert_eq!(new_york.at(instant), at(date(2026, 10, 15), 22, 0));
+        assert_eq!(
+            parse_due("tomorrow", new_york.at(instant)),
+            Some(day(date(2026, 10, 16)))
+        );
+        assert_eq!(
+            parse_due("tomorrow", tokyo.at(instant)),
+            Some(day(date(2026, 10, 17)))
+        );
+        assert!("Mars/Olympus".parse::<Timezone>().is_err());
+    }
+
+    #[test]
+    fn rejects_nonsense() {
+        for input in [
+            "",
+            "someday",
+            "next blursday",
+            "in 0 days",
+            "in 3 fortnights",
+            "tomorrow 25:00",
+            "13pm",
+            "feb 30",
+        ] {
+            assert_eq!(parse_due(input, now()), None, "{input}");
+        }
+    }
+}
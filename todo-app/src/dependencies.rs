@@ -0,0 +1,213 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    lists::TaskList,
+    task::Task,
+    tree::{self, TaskPath, MIN_ID_LEN},
+};
+
+/// Where a task with a given id sits.
+pub struct Entry<'a> {
+    pub list: &'a str,
+    pub path: TaskPath,
+    pub task: &'a Task,
+}
+
+/// Tasks of every list by id, to follow dependencies across lists and to
+/// pick tasks by a short id.
+pub struct TaskIndex<'a> {
+    entries: HashMap<&'a str, Entry<'a>>,
+    /// How much of each id is shown, so that no two tasks share a short id.
+    short_len: usize,
+}
+
+impl<'a> TaskIndex<'a> {
+    pub fn new(lists: &'a [TaskList]) -> Self {
+        let entries = lists
+            .iter()
+            .flat_map(|list| {
+                tree::flatten(&list.tasks)
+                    .into_iter()
+                    .map(move |(path, task)| {
+                        (
+                            task.id.as_str(),
+                            Entry {
+                                list: &list.name,
+                                path,
+                                task,
+                            },
+                        )
+                    })
+            })
+            .collect::<HashMap<_, _>>();
+
+        // Sorted, the longest prefix two ids share is between neighbours
+        let mut ids: Vec<&str> = entries.keys().copied().collect();
+        ids.sort_unstable();
+        let short_len = ids
+            .windows(2)
+            .map(|pair| {
+                let shared = pair[0]
+                    .bytes()
+                    .zip(pair[1].bytes())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                shared + 1
+            })
+            .fold(MIN_ID_LEN, usize::max);
+
+        Self { entries, short_len }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Entry<'a>> {
+        self.entries.get(id)
+    }
+
+    /// The start of the task's id that is enough to pick it.
+    pub fn short_id<'t>(&self, task: &'t Task) -> &'t str {
+        task.id.get(..self.short_len).unwrap_or(&task.id)
+    }
+
+    /// Tasks whose id starts with `prefix`; more than one if it is too short.
+    pub fn find(&self, prefix: &str) -> Vec<&Entry<'a>> {
+        self.entries
+            .iter()
+            .filter(|(id, _)| !id.is_empty() && id.starts_with(prefix))
+            .map(|(_, entry)| entry)
+            .collect()
+    }
+
+    /// The open tasks `task` waits for. Dependencies on deleted tasks are ignored.
+    pub fn blockers(&self, task: &Task) -> Vec<&Entry<'a>> {
+        task.depends_on
+            .iter()
+            .filter_map(|id| self.get(id))
+            .filter(|entry| !entry.task.is_completed())
+            .collect()
+    }
+
+    pub fn is_blocked(&self, task: &Task) -> bool {
+        !self.blockers(task).is_empty()
+    }
+
+    /// Whether `task` depending on `dependency` would close a loop, i.e.
+    /// `dependency` already waits for `task`, directly or through others.
+    pub fn creates_cycle(&self, task: &str, dependency: &str) -> bool {
+        let mut seen = HashSet::new();
+        let mut pending = vec![dependency];
+
+        while let Some(id) = pending.pop() {
+            if id == task {
+                return true;
+            }
+            if !seen.insert(id) {
+                continue;
+            }
+            if let Some(entry) = self.get(id) {
+                pending.extend(entry.task.depends_on.iter().map(String::as_str));
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::*;
+    use crate::task::Attributes;
+
+    /// A task with a fixed id, waiting for the tasks with the ids `on`.
+    fn task(id: &str, on: &[&str]) -> Task {
+        let mut task = Task::new(Attributes::parse(id, NaiveDateTime::default()).unwrap());
+        task.id = id.to_string();
+        task.depends_on = on.iter().map(|id| id.to_string()).collect();
+        task
+    }
+
+    fn list(name: &str, tasks: Vec<Task>) -> TaskList {
+        let mut list = TaskList::new(name);
+        list.tasks = tasks;
+        list
+    }
+
+    /// Ship waits for build and docs, build for design; design and spec
+    /// wait for each other, a loop left by a sync. Docs are in another list.
+    fn lists() -> Vec<TaskList> {
+        vec![
+            list(
+                "inbox",
+                vec![
+                    task("design", &[]),
+                    task("build", &["design"]),
+                    task("ship", &["build", "docs", "gone"]),
+                    task("loop1", &["loop2"]),
+                    task("loop2", &["loop1"]),
+                ],
+            ),
+            list("work", vec![task("docs", &["design"])]),
+        ]
+    }
+
+    #[test]
+    fn cycles_through_any_number_of_tasks() {
+        let lists = lists();
+        let index = TaskIndex::new(&lists);
+
+        assert!(index.creates_cycle("design", "design"));
+        assert!(index.creates_cycle("build", "ship"));
+        assert!(index.creates_cycle("design", "ship"));
+        // Across lists, through docs
+        assert!(index.creates_cycle("design", "docs"));
+    }
+
+    #[test]
+    fn shared_dependencies_are_no_cycle() {
+        let lists = lists();
+        let index = TaskIndex::new(&lists);
+
+        assert!(!index.creates_cycle("ship", "design"));
+        assert!(!index.creates_cycle("docs", "build"));
+        // A loop elsewhere neither counts nor hangs the search
+        assert!(!index.creates_cycle("design", "loop1"));
+        assert!(!index.creates_cycle("design", "gone"));
+    }
+
+    #[test]
+    fn only_open_tasks_block() {
+        let mut lists = lists();
+        lists[0].tasks[1].complete();
+        let index = TaskIndex::new(&lists);
+        let ship = &lists[0].tasks[2];
+
+        // Build is done and the task gone was deleted
+        let blockers: Vec<&str> = index
+            .blockers(ship)
+            .iter()
+            .map(|entry| entry.task.id.as_str())
+            .collect();
+        assert_eq!(blockers, ["docs"]);
+        assert_eq!(index.get("docs").unwrap().list, "work");
+        assert!(!index.is_blocked(&lists[0].tasks[0]));
+    }
+
+    #[test]
+    fn short_ids_grow_until_unique() {
+        let lists = vec![list(
+            "inbox",
+            vec![
+                task("3fa91111", &[]),
+                task("3fa92222", &[]),
+                task("7c00", &[]),
+            ],
+        )];
+        let index = TaskIndex::new(&lists);
+
+        assert_eq!(index.short_id(&lists[0].tasks[0]), "3fa91");
+        assert_eq!(index.short_id(&lists[0].tasks[2]), "7c00");
+        assert_eq!(index.find("3fa9").len(), 2);
+        assert_eq!(index.find("3fa92").len(), 1);
+    }
+}
@@ -0,0 +1,71 @@
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    process::Command,
+};
+
+use todo_core::TodoError;
+
+/// Used when neither `VISUAL` nor `EDITOR` is set.
+const FALLBACK_EDITOR: &str = "vi";
+/// How many random names are tried for the temporary file.
+const TEMP_TRIES: usize = 3;
+
+/// Lets the user change `text` in `$VISUAL` or `$EDITOR`, which may carry
+/// arguments such as `code --wait`. The text goes through a temporary file
+/// only the user can read, removed again afterwards.
+pub fn edit(text: &str) -> Result<String, TodoError> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| FALLBACK_EDITOR.to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or(FALLBACK_EDITOR);
+
+    // Ends the last line, so text added in the editor starts on a new one
+    let text = match text {
+        "" => String::new(),
+        text => format!("{text}\n"),
+    };
+    let (path, mut file) = create_temp().map_err(TodoError::Io)?;
+    if let Err(err) = file.write_all(text.as_bytes()) {
+        let _ = fs::remove_file(&path);
+        return Err(TodoError::Io(err));
+    }
+    drop(file);
+
+    let result = match Command::new(program).args(words).arg(&path).status() {
+        Ok(status) if status.success() => fs::read_to_string(&path).map_err(TodoError::Io),
+        Ok(status) => Err(TodoError::InvalidInput(format!(
+            "{program} exited with {status}, nothing was changed"
+        ))),
+        Err(err) => Err(TodoError::InvalidInput(format!(
+            "Could not run {program}: {err} (set EDITOR to the editor to use)"
+        ))),
+    };
+
+    let _ = fs::remove_file(&path);
+    result
+}
+
+/// Creates a new Markdown file, so editors highlight the note, under a
+/// random name that other users of the temp directory cannot claim first.
+fn create_temp() -> io::Result<(PathBuf, File)> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut tries = 1;
+    loop {
+        let name = format!("todo-note-{}.md", uuid::Uuid::new_v4().simple());
+        let path = env::temp_dir().join(name);
+        match options.open(&path) {
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists && tries < TEMP_TRIES => {
+                tries += 1;
+            }
+            result => return result.map(|file| (path, file)),
+        }
+    }
+}
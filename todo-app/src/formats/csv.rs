@@ -0,0 +1,193 @@
+//! Comma-separated values with a header row. The `path` column holds the
+//! dotted index, so subtasks keep their place under their parent.
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    recurrence::Recurrence,
+    task::{new_id, Due, Priority, Task},
+    timelog::Effort,
+    tree::{self, TaskPath},
+    workflow,
+};
+
+#[derive(Serialize, Deserialize)]
+struct Row {
+    #[serde(default)]
+    path: String,
+    description: String,
+    #[serde(default)]
+    completed: bool,
+    /// The workflow state; files without the column go by `completed`
+    #[serde(default)]
+    state: String,
+    #[serde(default)]
+    priority: String,
+    #[serde(default)]
+    due: String,
+    /// Space-separated, as tags cannot contain spaces
+    #[serde(default)]
+    tags: String,
+    #[serde(default)]
+    recurrence: String,
+    #[serde(default)]
+    estimate: String,
+    /// Logged time in minutes; written for reports, not read back
+    #[serde(default)]
+    spent_minutes: i64,
+    #[serde(default)]
+    created_at: String,
+    #[serde(default)]
+    completed_at: String,
+    /// Markdown, line breaks included
+    #[serde(default)]
+    note: String,
+    /// One per line, as paths can contain spaces
+    #[serde(default)]
+    attachments: String,
+}
+
+pub fn export(tasks: &[Task]) -> Result<String, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    for (path, task) in tree::flatten(tasks) {
+        writer
+            .serialize(Row {
+                path: path.to_string(),
+                description: task.description.clone(),
+                completed: task.is_completed(),
+                state: task.state.clone(),
+                priority: task.priority.map(|p| p.to_string()).unwrap_or_default(),
+                due: task.due.map(|due| due.to_string()).unwrap_or_default(),
+                tags: task.tags.join(" "),
+                recurrence: task
+                    .recurrence
+                    .as_ref()
+                    .map(|r| r.to_string())
+                    .unwrap_or_default(),
+                estimate: task.estimate.map(|e| e.to_string()).unwrap_or_default(),
+                spent_minutes: task.spent(Local::now()).num_minutes(),
+                created_at: task.created_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+                completed_at: task
+                    .completed_at
+                    .map(|t| t.to_rfc3339())
+                    .unwrap_or_default(),
+                note: task.note.clone(),
+                attachments: task.attachments.join("\n"),
+            })
+            .map_err(|err| err.to_string())?;
+    }
+
+    let bytes = writer.into_inner().map_err(|err| err.to_string())?;
+    String::from_utf8(bytes).map_err(|err| err.to_string())
+}
+
+pub fn import(input: &str) -> Result<Vec<Task>, String> {
+    let mut reader = csv::Reader::from_reader(input.as_bytes());
+    let mut tasks: Vec<Task> = Vec::new();
+
+    for (number, row) in reader.deserialize::<Row>().enumerate() {
+        // Line 1 is the header
+        let line = number + 2;
+        let row = row.map_err(|err| format!("Line {line}: {err}"))?;
+        let task = parse_row(&row).map_err(|err| format!("Line {line}: {err}"))?;
+
+        let parent = match row.path.trim() {
+            "" => None,
+            path => path.parse::<TaskPath>()?.parent(),
+        };
+
+        match parent {
+            Some(parent) => tree::find_mut(&mut tasks, &parent)
+                .ok_or_else(|| format!("Line {line}: parent task {parent} is missing"))?
+                .subtasks
+                .push(task),
+            None => tasks.push(task),
+        }
+    }
+
+    tree::sync_all(&mut tasks);
+    Ok(tasks)
+}
+
+fn optional(value: &str) -> Option<&str> {
+    Some(value.trim()).filter(|value| !value.is_empty())
+}
+
+fn parse_row(row: &Row) -> Result<Task, String> {
+    let timestamp = |value: &str| -> Result<Option<DateTime<Local>>, String> {
+        optional(value)
+            .map(|value| {
+                DateTime::parse_from_rfc3339(value)
+                    .map(|time| time.with_timezone(&Local))
+                    .map_err(|_| format!("Invalid timestamp: {value}"))
+            })
+            .transpose()
+    };
+
+    if row.description.trim().is_empty() {
+        return Err("Description cannot be empty".to_string());
+    }
+
+    Ok(Task {
+        id: new_id(),
+        description: row.description.trim().to_string(),
+        state: match optional(&row.state) {
+            Some(state) => state.to_lowercase(),
+            None if row.completed => workflow::DONE.to_string(),
+            None => workflow::TODO.to_string(),
+        },
+        due: optional(&row.due)
+            .map(|value| Due::parse(value).ok_or_else(|| format!("Invalid due date: {value}")))
+            .transpose()?,
+        priority: optional(&row.priority)
+            .map(|value| Priority::parse(value).ok_or_else(|| format!("Invalid priority: {value}")))
+            .transpose()?,
+        tags: row.tags.split_whitespace().map(str::to_string).collect(),
+        recurrence: optional(&row.recurrence)
+            .map(str::parse::<Recurrence>)
+            .transpose()?,
+        estimate: optional(&row.estimate)
+            .map(str::parse::<Effort>)
+            .transpose()?,
+        work: Vec::new(),
+        depends_on: Vec::new(),
+        snoozed_until: None,
+        created_at: timestamp(&row.created_at)?,
+        completed_at: timestamp(&row.completed_at)?,
+        note: row.note.trim_end().to_string(),
+        attachments: row
+            .attachments
+            .lines()
+            .map(str::trim)
+            .filter(|attachment| !attachment.is_empty())
+            .map(str::to_string)
+            .collect(),
+        subtasks: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::fixture::{self, kept};
+
+    #[test]
+    fn round_trip_keeps_tasks() {
+        let tasks = fixture::tasks();
+
+        let imported = import(&export(&tasks).unwrap()).unwrap();
+
+        assert_eq!(kept(&imported), kept(&tasks));
+    }
+
+    #[test]
+    fn import_reports_line_of_invalid_row() {
+        let input = "path,description,due\n1,Deploy api,2026-11-01\n2,Send invoice,someday\n";
+
+        let err = import(input).unwrap_err();
+
+        assert_eq!(err, "Line 3: Invalid due date: someday");
+    }
+}
@@ -0,0 +1,153 @@
+//! GitHub-flavored Markdown checklists: `- [ ]` for open and `- [x]` for
+//! completed tasks, with subtasks indented by two spaces per level. Metadata
+//! is kept in the inline syntax, e.g. `- [ ] Deploy api due:2026-11-01 +backend`.
+//! Notes follow their task, indented one level deeper, and attachments are
+//! links below them, e.g. `  - [spec.pdf](</home/me/spec.pdf>)`.
+
+use chrono::NaiveDateTime;
+use regex::Regex;
+use std::path::Path;
+
+use crate::{
+    task::{self, Attributes, Task},
+    tree::{self, TaskPath},
+};
+
+const INDENT: &str = "  ";
+
+pub fn export(tasks: &[Task]) -> String {
+    let mut output = String::new();
+
+    for (path, task) in tree::flatten(tasks) {
+        let checkbox = if task.is_completed() { "[x]" } else { "[ ]" };
+        let indent = INDENT.repeat(path.depth());
+        output.push_str(&format!("{indent}- {checkbox} {}\n", task.inline()));
+
+        for line in task.note.lines() {
+            match line.trim_end() {
+                "" => output.push('\n'),
+                line => output.push_str(&format!("{indent}{INDENT}{line}\n")),
+            }
+        }
+        for attachment in &task.attachments {
+            output.push_str(&format!(
+                "{indent}{INDENT}- [{}](<{attachment}>)\n",
+                link_text(attachment)
+            ));
+        }
+    }
+
+    output
+}
+
+/// A URL as it is, a file by its name.
+fn link_text(attachment: &str) -> &str {
+    if task::is_url(attachment) {
+        return attachment;
+    }
+
+    Path::new(attachment)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(attachment)
+}
+
+/// Reads every checklist item, with the note and links indented below it.
+/// Other lines such as headings or prose are skipped.
+pub fn import(input: &str, now: NaiveDateTime) -> Result<Vec<Task>, String> {
+    let item = Regex::new(r"^(\s*)[-*+] \[([ xX])\] (.*)$").map_err(|err| err.to_string())?;
+    let link = Regex::new(r"^[-*+] \[.*\]\(<?([^<>]*?)>?\)$").map_err(|err| err.to_string())?;
+
+    let mut tasks: Vec<Task> = Vec::new();
+    // Indentation and path of the items the next one may be nested under
+    let mut parents: Vec<(usize, TaskPath)> = Vec::new();
+    // Blank lines seen since the last line of a note, kept if it goes on
+    let mut blank_lines = 0;
+
+    for (number, line) in input.lines().enumerate() {
+        let Some(captures) = item.captures(line) else {
+            if line.trim().is_empty() {
+                blank_lines += 1;
+                continue;
+            }
+
+            let line = line.replace('\t', INDENT);
+            let indent = line.len() - line.trim_start().len();
+            let Some((level, path)) = parents.last().filter(|(level, _)| indent > *level) else {
+                blank_lines = 0;
+                continue;
+            };
+            let Some(task) = tree::find_mut(&mut tasks, path) else {
+                continue;
+            };
+
+            // Deeper indentation within the note is kept
+            let text = line[indent.min(level + INDENT.len())..].trim_end();
+            if let Some(captures) = link.captures(text) {
+                task.attachments.push(captures[1].to_string());
+            } else {
+                if !task.note.is_empty() {
+                    task.note.push_str(&"\n".repeat(blank_lines + 1));
+                }
+                task.note.push_str(text);
+            }
+            blank_lines = 0;
+            continue;
+        };
+        blank_lines = 0;
+
+        let indent = captures[1].replace('\t', INDENT).len();
+        let attributes = Attributes::parse(&captures[3], now)
+            .map_err(|err| format!("Line {}: {err}", number + 1))?;
+
+        let mut task = Task::new(attributes);
+        if &captures[2] != " " {
+            task.complete();
+        }
+
+        while parents.last().is_some_and(|(level, _)| *level >= indent) {
+            parents.pop();
+        }
+
+        let path = match parents
+            .last()
+            .and_then(|(_, parent)| tree::find_mut(&mut tasks, parent).map(|found| (parent, found)))
+        {
+            Some((parent, found)) => {
+                found.subtasks.push(task);
+                parent.child(found.subtasks.len() - 1)
+            }
+            None => {
+                tasks.push(task);
+                TaskPath::top(tasks.len() - 1)
+            }
+        };
+
+        parents.push((indent, path));
+    }
+
+    tree::sync_all(&mut tasks);
+    Ok(tasks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::fixture::{self, kept, Kept};
+
+    #[test]
+    fn round_trip_keeps_tasks() {
+        let tasks = fixture::tasks();
+
+        let imported = import(&export(&tasks), fixture::now()).unwrap();
+
+        // Checklists carry no dates
+        let without_dates = |tasks: &[Task]| {
+            kept(tasks)
+                .into_iter()
+                .map(Kept::without_dates)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(without_dates(&imported), without_dates(&tasks));
+    }
+}
@@ -0,0 +1,167 @@
+mod csv;
+mod markdown;
+mod todotxt;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use std::{path::Path, str::FromStr};
+
+use crate::task::Task;
+
+/// File formats tasks can be imported from and exported to.
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+    TodoTxt,
+    Markdown,
+    Csv,
+}
+
+impl Format {
+    /// Guesses the format from a file extension: `.txt`, `.md` or `.csv`.
+    pub fn from_path(path: &Path) -> Option<Format> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "txt" => Some(Format::TodoTxt),
+            "md" | "markdown" => Some(Format::Markdown),
+            "csv" => Some(Format::Csv),
+            _ => None,
+        }
+    }
+
+    pub fn export(&self, tasks: &[Task]) -> Result<String, String> {
+        match self {
+            Format::TodoTxt => Ok(todotxt::export(tasks)),
+            Format::Markdown => Ok(markdown::export(tasks)),
+            Format::Csv => csv::export(tasks),
+        }
+    }
+
+    /// Reads tasks from `input`; relative due dates are resolved against `now`.
+    pub fn import(&self, input: &str, now: NaiveDateTime) -> Result<Vec<Task>, String> {
+        match self {
+            Format::TodoTxt => todotxt::import(input, now),
+            Format::Markdown => markdown::import(input, now),
+            Format::Csv => csv::import(input),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.to_lowercase().as_str() {
+            "todotxt" | "todo.txt" | "txt" => Ok(Format::TodoTxt),
+            "markdown" | "md" => Ok(Format::Markdown),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!(
+                "Invalid format: {input} (expected todotxt, markdown or csv)"
+            )),
+        }
+    }
+}
+
+/// Formats that only carry a date store timestamps as local midnight.
+fn start_of_day(date: NaiveDate) -> Option<DateTime<Local>> {
+    Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .earliest()
+}
+
+/// Tasks and a comparison shared by the round-trip tests of each format.
+#[cfg(test)]
+mod fixture {
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    use super::start_of_day;
+    use crate::task::{Attributes, Due, Priority, Task};
+
+    pub fn now() -> NaiveDateTime {
+        date(2026, 10, 18).and_hms_opt(9, 30, 0).unwrap()
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn task(inline: &str, created: NaiveDate) -> Task {
+        let mut task = Task::new(Attributes::parse(inline, now()).unwrap());
+        task.created_at = start_of_day(created);
+        task
+    }
+
+    /// Open, completed and nested tasks with every field the formats carry.
+    pub fn tasks() -> Vec<Task> {
+        let mut deploy = task(
+            "Deploy api due:2026-11-01T17:00 !high +backend +ops est:2h",
+            date(2026, 10, 1),
+        );
+        deploy.note = "# Steps\n\n1. build 100%\n   - keep this indented\n2. ship".to_string();
+        deploy.attachments = vec![
+            "/home/me/spec file.pdf".to_string(),
+            "https://example.com/issue/42".to_string(),
+        ];
+
+        let mut invoice = task("Send invoice !medium +billing", date(2026, 9, 20));
+        invoice.complete();
+        invoice.completed_at = start_of_day(date(2026, 10, 2));
+
+        let mut notes = task("Write release notes !low", date(2026, 10, 3));
+        notes.complete();
+        notes.completed_at = start_of_day(date(2026, 10, 4));
+        let mut tag = task("Tag the release due:2026-10-30", date(2026, 10, 3));
+        tag.note = "Only after the notes are out".to_string();
+        tag.subtasks = vec![task("Push the tag +git", date(2026, 10, 5))];
+        let mut release = task("Release 1.2 repeat:monthly", date(2026, 10, 2));
+        release.subtasks = vec![notes, tag];
+
+        vec![deploy, invoice, release]
+    }
+
+    /// What a format keeps of a task; ids are new on every import.
+    #[derive(Debug, PartialEq)]
+    pub struct Kept {
+        description: String,
+        state: String,
+        due: Option<Due>,
+        priority: Option<Priority>,
+        tags: Vec<String>,
+        recurrence: Option<String>,
+        estimate: Option<String>,
+        created: Option<NaiveDate>,
+        completed: Option<NaiveDate>,
+        note: String,
+        attachments: Vec<String>,
+        subtasks: Vec<Kept>,
+    }
+
+    impl Kept {
+        /// For formats without creation and completion dates.
+        pub fn without_dates(self) -> Kept {
+            Kept {
+                created: None,
+                completed: None,
+                subtasks: self.subtasks.into_iter().map(Kept::without_dates).collect(),
+                ..self
+            }
+        }
+    }
+
+    pub fn kept(tasks: &[Task]) -> Vec<Kept> {
+        tasks
+            .iter()
+            .map(|task| Kept {
+                description: task.description.clone(),
+                state: task.state.clone(),
+                due: task.due,
+                priority: task.priority,
+                tags: task.tags.clone(),
+                recurrence: task.recurrence.as_ref().map(ToString::to_string),
+                estimate: task.estimate.map(|estimate| estimate.to_string()),
+                created: task.created_at.map(|created| created.date_naive()),
+                completed: task.completed_at.map(|completed| completed.date_naive()),
+                note: task.note.clone(),
+                attachments: task.attachments.clone(),
+                subtasks: kept(&task.subtasks),
+            })
+            .collect()
+    }
+}
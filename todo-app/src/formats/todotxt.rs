@@ -0,0 +1,221 @@
+//! The todo.txt format (http://todotxt.org): one task per line, e.g.
+//! `x 2026-10-20 2026-10-01 Deploy api +backend due:2026-11-01 pri:A`.
+//! Subtasks are written as lines of their own. Notes and attachments go in
+//! `note:` and `att:` tags, with spaces, line breaks and `%` percent-encoded.
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use super::start_of_day;
+use crate::{
+    task::{Attributes, Priority, Task},
+    tree, workflow,
+};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+pub fn export(tasks: &[Task]) -> String {
+    tree::flatten(tasks)
+        .into_iter()
+        .map(|(_, task)| line(task) + "\n")
+        .collect()
+}
+
+pub fn import(input: &str, now: NaiveDateTime) -> Result<Vec<Task>, String> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            parse_line(line, now).map_err(|err| format!("Line {}: {err}", number + 1))
+        })
+        .collect()
+}
+
+fn line(task: &Task) -> String {
+    let mut words = Vec::new();
+
+    if task.is_completed() {
+        words.push("x".to_string());
+        if let Some(completed_at) = task.completed_at {
+            words.push(completed_at.format(DATE_FORMAT).to_string());
+        }
+    } else if let Some(priority) = task.priority {
+        words.push(format!("({})", priority_letter(priority)));
+    }
+
+    // A single date after `x` is read as the completion date, so the creation
+    // date can only follow one
+    if !task.is_completed() || task.completed_at.is_some() {
+        if let Some(created_at) = task.created_at {
+            words.push(created_at.format(DATE_FORMAT).to_string());
+        }
+    }
+
+    words.push(task.description.clone());
+    words.extend(task.tags.iter().map(|tag| format!("+{tag}")));
+
+    if let Some(due) = task.due {
+        words.push(format!("due:{due}"));
+    }
+    if let Some(recurrence) = &task.recurrence {
+        words.push(format!("rec:{recurrence}"));
+    }
+    if let Some(estimate) = task.estimate {
+        words.push(format!("est:{estimate}"));
+    }
+    // todo.txt only knows done or not, so other workflow states go in a tag
+    if !task.is_completed() && task.state != workflow::TODO {
+        words.push(format!("state:{}", task.state));
+    }
+    // Completed tasks keep their priority as a tag, as todo.txt suggests
+    if let (true, Some(priority)) = (task.is_completed(), task.priority) {
+        words.push(format!("pri:{}", priority_letter(priority)));
+    }
+    if !task.note.is_empty() {
+        words.push(format!("note:{}", encode(&task.note)));
+    }
+    for attachment in &task.attachments {
+        words.push(format!("att:{}", encode(attachment)));
+    }
+
+    words.join(" ")
+}
+
+fn parse_line(line: &str, now: NaiveDateTime) -> Result<Task, String> {
+    let mut words = line.split_whitespace().peekable();
+    let is_completed = words.next_if_eq(&"x").is_some();
+    // Some tools keep the priority in front of completed tasks too
+    let mut priority = words
+        .next_if(|word| parse_priority(word).is_some())
+        .and_then(parse_priority);
+    let completed_on = if is_completed {
+        words.next_if(|word| parse_date(word).is_some())
+    } else {
+        None
+    };
+    let created_on = words.next_if(|word| parse_date(word).is_some());
+
+    let mut state = None;
+    let mut note = String::new();
+    let mut attachments = Vec::new();
+    let mut rest = Vec::new();
+    for word in words {
+        if let Some(text) = word.strip_prefix("note:") {
+            note = decode(text)?;
+        } else if let Some(attachment) = word.strip_prefix("att:") {
+            attachments.push(decode(attachment)?);
+        } else if let Some(letter) = word.strip_prefix("pri:") {
+            priority = priority_from_letter(letter);
+        } else if let Some(name) = word.strip_prefix("state:") {
+            state = Some(name.to_lowercase());
+        } else if let Some(rule) = word.strip_prefix("rec:") {
+            rest.push(format!("repeat:{}", rule.trim_start_matches('+')));
+        } else {
+            rest.push(word.to_string());
+        }
+    }
+
+    let attributes = Attributes::parse(&rest.join(" "), now)?;
+    let mut task = Task::new(attributes);
+
+    if priority.is_some() {
+        task.priority = priority;
+    }
+    task.note = note;
+    task.attachments = attachments;
+    if let Some(created_on) = created_on.and_then(parse_date) {
+        task.created_at = start_of_day(created_on);
+    }
+    if is_completed {
+        task.state = workflow::DONE.to_string();
+        task.completed_at = completed_on.and_then(parse_date).and_then(start_of_day);
+    } else if let Some(state) = state {
+        task.set_state(&state);
+    }
+
+    Ok(task)
+}
+
+/// Makes text fit in a single todo.txt word.
+fn encode(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace(' ', "%20")
+        .replace('\t', "%09")
+        .replace('\r', "")
+        .replace('\n', "%0A")
+}
+
+fn decode(word: &str) -> Result<String, String> {
+    let mut bytes = Vec::new();
+    let mut rest = word.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let code = tail
+                .get(..2)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| format!("Invalid escape in {word}"))?;
+            bytes.push(code);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+
+    String::from_utf8(bytes).map_err(|_| format!("Invalid escape in {word}"))
+}
+
+fn parse_date(word: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(word, DATE_FORMAT).ok()
+}
+
+fn parse_priority(word: &str) -> Option<Priority> {
+    word.strip_prefix('(')?
+        .strip_suffix(')')
+        .and_then(priority_from_letter)
+}
+
+fn priority_letter(priority: Priority) -> char {
+    match priority {
+        Priority::High => 'A',
+        Priority::Medium => 'B',
+        Priority::Low => 'C',
+    }
+}
+
+/// todo.txt has priorities A to Z; everything below B counts as low.
+fn priority_from_letter(letter: &str) -> Option<Priority> {
+    match letter {
+        "A" => Some(Priority::High),
+        "B" => Some(Priority::Medium),
+        _ if letter.len() == 1 && letter.chars().all(|c| c.is_ascii_uppercase()) => {
+            Some(Priority::Low)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::fixture::{self, kept};
+
+    #[test]
+    fn round_trip_keeps_tasks() {
+        let tasks = fixture::tasks();
+
+        let imported = import(&export(&tasks), fixture::now()).unwrap();
+
+        // todo.txt has no nesting, so subtasks come back after their parent
+        let flat: Vec<Task> = tree::flatten(&tasks)
+            .into_iter()
+            .map(|(_, task)| Task {
+                subtasks: Vec::new(),
+                ..task.clone()
+            })
+            .collect();
+        assert_eq!(kept(&imported), kept(&flat));
+    }
+}
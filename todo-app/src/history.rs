@@ -0,0 +1,507 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    lists::{self, TaskList},
+    task::Task,
+    tree::{self, TaskPath},
+};
+
+/// How many operations are kept for undo.
+const HISTORY_LIMIT: usize = 100;
+
+/// A recorded change to the task lists, holding enough to reverse it.
+/// Task changes name the list they apply to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+    Insert {
+        list: String,
+        path: TaskPath,
+        task: Task,
+    },
+    Remove {
+        list: String,
+        path: TaskPath,
+        task: Task,
+    },
+    /// Boxed, as a task with its work log and subtasks can be large.
+    Replace {
+        list: String,
+        path: TaskPath,
+        before: Box<Task>,
+        after: Box<Task>,
+    },
+    InsertList {
+        position: usize,
+        list: TaskList,
+    },
+    RemoveList {
+        position: usize,
+        list: TaskList,
+    },
+    RenameList {
+        from: String,
+        to: String,
+    },
+    /// Moves a top-level task with its subtasks from a list into its archive.
+    Archive {
+        list: String,
+        path: TaskPath,
+        position: usize,
+        task: Task,
+    },
+    /// Moves an archived task back into its list.
+    Restore {
+        list: String,
+        position: usize,
+        path: TaskPath,
+        task: Task,
+    },
+    /// Deletes a task from the archive.
+    Purge {
+        list: String,
+        position: usize,
+        task: Task,
+    },
+    /// Puts a purged task back into the archive.
+    Unpurge {
+        list: String,
+        position: usize,
+        task: Task,
+    },
+    /// Several changes that are applied and undone together.
+    Batch {
+        operations: Vec<Operation>,
+    },
+}
+
+impl Operation {
+    pub fn apply(&self, lists: &mut Vec<TaskList>) -> Result<(), String> {
+        let mismatch = || "History does not match the task list".to_string();
+
+        let (list, path) = match self {
+            Operation::Insert { list, path, task } => {
+                let items = lists::tasks_mut(lists, list).ok_or_else(mismatch)?;
+                let siblings = tree::siblings_mut(items, path)
+                    .filter(|siblings| path.last() <= siblings.len())
+                    .ok_or_else(mismatch)?;
+                siblings.insert(path.last(), task.clone());
+                (list, path)
+            }
+            Operation::Remove { list, path, task } => {
+                let items = lists::tasks_mut(lists, list).ok_or_else(mismatch)?;
+                let siblings = tree::siblings_mut(items, path)
+                    .filter(|siblings| path.last() < siblings.len())
+                    .filter(|siblings| same_task(&siblings[path.last()], task))
+                    .ok_or_else(mismatch)?;
+                siblings.remove(path.last());
+                (list, path)
+            }
+            Operation::Replace {
+                list,
+                path,
+                before,
+                after,
+            } => {
+                let items = lists::tasks_mut(lists, list).ok_or_else(mismatch)?;
+                let task = tree::find_mut(items, path)
+                    .filter(|task| same_task(task, before))
+                    .ok_or_else(mismatch)?;
+                let id = std::mem::take(&mut task.id);
+                *task = (**after).clone();
+                // Changes recorded before tasks had ids keep the current one
+                if task.id.is_empty() {
+                    task.id = id;
+                }
+                (list, path)
+            }
+            Operation::Archive {
+                list,
+                path,
+                position,
+                task,
+            } => {
+                let archive = lists::archive_mut(lists, list).ok_or_else(mismatch)?;
+                if *position > archive.len() {
+                    return Err(mismatch());
+                }
+                let items = lists::tasks_mut(lists, list).ok_or_else(mismatch)?;
+                let siblings = tree::siblings_mut(items, path)
+                    .filter(|siblings| path.last() < siblings.len())
+                    .filter(|siblings| same_task(&siblings[path.last()], task))
+                    .ok_or_else(mismatch)?;
+                let archived = siblings.remove(path.last());
+                if let Some(archive) = lists::archive_mut(lists, list) {
+                    archive.insert(*position, archived);
+                }
+                (list, path)
+            }
+            Operation::Restore {
+                list,
+                position,
+                path,
+                task,
+            } => {
+                let items = lists::tasks_mut(lists, list).ok_or_else(mismatch)?;
+                tree::siblings_mut(items, path)
+                    .filter(|siblings| path.last() <= siblings.len())
+                    .ok_or_else(mismatch)?;
+                let archive = lists::archive_mut(lists, list)
+                    .filter(|archive| *position < archive.len())
+                    .filter(|archive| same_task(&archive[*position], task))
+                    .ok_or_else(mismatch)?;
+                let restored = archive.remove(*position);
+                let items = lists::tasks_mut(lists, list).ok_or_else(mismatch)?;
+                if let Some(siblings) = tree::siblings_mut(items, path) {
+                    siblings.insert(path.last(), restored);
+                }
+                (list, path)
+            }
+            Operation::Purge {
+                list,
+                position,
+                task,
+            } => {
+                let archive = lists::archive_mut(lists, list)
+                    .filter(|archive| *position < archive.len())
+                    .filter(|archive| same_task(&archive[*position], task))
+                    .ok_or_else(mismatch)?;
+                archive.remove(*position);
+                return Ok(());
+            }
+            Operation::Unpurge {
+                list,
+                position,
+                task,
+            } => {
+                let archive = lists::archive_mut(lists, list)
+                    .filter(|archive| *position <= archive.len())
+                    .ok_or_else(mismatch)?;
+                archive.insert(*position, task.clone());
+                return Ok(());
+            }
+            Operation::InsertList { position, list } => {
+                if *position > lists.len() || lists::position(lists, &list.name).is_some() {
+                    return Err(mismatch());
+                }
+                lists.insert(*position, list.clone());
+                return Ok(());
+            }
+            Operation::RemoveList { position, list } => {
+                if lists::position(lists, &list.name) != Some(*position) {
+                    return Err(mismatch());
+                }
+                lists.remove(*position);
+                return Ok(());
+            }
+            Operation::RenameList { from, to } => {
+                let position = lists::position(lists, from).ok_or_else(mismatch)?;
+                if lists::position(lists, to).is_some() {
+                    return Err(mismatch());
+                }
+                lists[position].name = to.clone();
+                return Ok(());
+            }
+            Operation::Batch { operations } => {
+                // Apply to a copy so a failing step leaves `lists` untouched
+                let mut staged = lists.clone();
+                for operation in operations {
+                    operation.apply(&mut staged)?;
+                }
+                *lists = staged;
+                return Ok(());
+            }
+        };
+
+        if let Some(items) = lists::tasks_mut(lists, list) {
+            tree::sync_ancestors(items, path);
+        }
+        Ok(())
+    }
+
+    pub fn inverse(&self) -> Operation {
+        match self.clone() {
+            Operation::Insert { list, path, task } => Operation::Remove { list, path, task },
+            Operation::Remove { list, path, task } => Operation::Insert { list, path, task },
+            Operation::Replace {
+                list,
+                path,
+                before,
+                after,
+            } => Operation::Replace {
+                list,
+                path,
+                before: after,
+                after: before,
+            },
+            Operation::Archive {
+                list,
+                path,
+                position,
+                task,
+            } => Operation::Restore {
+                list,
+                position,
+                path,
+                task,
+            },
+            Operation::Restore {
+                list,
+                position,
+                path,
+                task,
+            } => Operation::Archive {
+                list,
+                path,
+                position,
+                task,
+            },
+            Operation::Purge {
+                list,
+                position,
+                task,
+            } => Operation::Unpurge {
+                list,
+                position,
+                task,
+            },
+            Operation::Unpurge {
+                list,
+                position,
+                task,
+            } => Operation::Purge {
+                list,
+                position,
+                task,
+            },
+            Operation::InsertList { position, list } => Operation::RemoveList { position, list },
+            Operation::RemoveList { position, list } => Operation::InsertList { position, list },
+            Operation::RenameList { from, to } => Operation::RenameList { from: to, to: from },
+            Operation::Batch { operations } => Operation::Batch {
+                operations: operations.iter().rev().map(Operation::inverse).collect(),
+            },
+        }
+    }
+
+    /// List renames done by this operation, in the order they are applied.
+    pub fn renames(&self) -> Vec<(String, String)> {
+        match self {
+            Operation::RenameList { from, to } => vec![(from.clone(), to.clone())],
+            Operation::Batch { operations } => {
+                operations.iter().flat_map(Operation::renames).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Operation::Insert { task, .. } => format!("add \"{}\"", task.description),
+            Operation::Remove { task, .. } => format!("delete \"{}\"", task.description),
+            Operation::Replace { before, .. } => format!("change to \"{}\"", before.description),
+            Operation::Archive { task, .. } => format!("archive \"{}\"", task.description),
+            Operation::Restore { task, .. } => format!("restore \"{}\"", task.description),
+            Operation::Purge { task, .. } => format!("purge \"{}\"", task.description),
+            Operation::Unpurge { task, .. } => {
+                format!("put \"{}\" back into the archive", task.description)
+            }
+            Operation::InsertList { list, .. } => format!("create list {}", list.name),
+            Operation::RemoveList { list, .. } => format!("delete list {}", list.name),
+            Operation::RenameList { from, to } => format!("rename list {from} to {to}"),
+            // Bulk changes can touch hundreds of tasks
+            Operation::Batch { operations } if operations.len() > 3 => format!(
+                "{} and {} more changes",
+                operations[0].describe(),
+                operations.len() - 1
+            ),
+            Operation::Batch { operations } => operations
+                .iter()
+                .map(Operation::describe)
+                .collect::<Vec<_>>()
+                .join(" and "),
+        }
+    }
+}
+
+/// Whether `found` is the task an operation was recorded for. Paths can
+/// point at another task once a sync merged in changes from elsewhere.
+/// Tasks recorded before they had ids cannot be told apart.
+fn same_task(found: &Task, recorded: &Task) -> bool {
+    found.id.is_empty() || recorded.id.is_empty() || found.id == recorded.id
+}
+
+/// Undo and redo stacks, saved in the data file next to the tasks.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct History {
+    #[serde(default)]
+    undo: Vec<Operation>,
+    #[serde(default)]
+    redo: Vec<Operation>,
+}
+
+impl History {
+    /// Stores an operation that was just applied. A new change discards the redo stack.
+    pub fn record(&mut self, operation: Operation) {
+        self.undo.push(operation);
+        self.redo.clear();
+
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+    }
+
+    pub fn undo(&mut self, lists: &mut Vec<TaskList>) -> Result<Operation, String> {
+        let operation = self.undo.pop().ok_or("Nothing to undo")?;
+
+        if let Err(err) = operation.inverse().apply(lists) {
+            self.undo.push(operation);
+            return Err(err);
+        }
+
+        self.redo.push(operation.clone());
+        Ok(operation)
+    }
+
+    pub fn redo(&mut self, lists: &mut Vec<TaskList>) -> Result<Operation, String> {
+        let operation = self.redo.pop().ok_or("Nothing to redo")?;
+
+        if let Err(err) = operation.apply(lists) {
+            self.redo.push(operation);
+            return Err(err);
+        }
+
+        self.undo.push(operation.clone());
+        Ok(operation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::*;
+    use crate::task::Attributes;
+
+    fn task(description: &str) -> Task {
+        Task::new(Attributes::parse(description, NaiveDateTime::default()).unwrap())
+    }
+
+    fn lists(descriptions: &[&str]) -> Vec<TaskList> {
+        let mut list = TaskList::new("inbox");
+        list.tasks = descriptions
+            .iter()
+            .map(|description| task(description))
+            .collect();
+        vec![list]
+    }
+
+    fn descriptions(lists: &[TaskList]) -> Vec<&str> {
+        lists[0]
+            .tasks
+            .iter()
+            .map(|task| task.description.as_str())
+            .collect()
+    }
+
+    fn insert(position: usize, description: &str) -> Operation {
+        Operation::Insert {
+            list: "inbox".to_string(),
+            path: TaskPath::top(position),
+            task: task(description),
+        }
+    }
+
+    fn record(history: &mut History, lists: &mut Vec<TaskList>, operation: Operation) {
+        operation.apply(lists).unwrap();
+        history.record(operation);
+    }
+
+    #[test]
+    fn undo_and_redo_in_turn() {
+        let mut lists = lists(&["Design"]);
+        let mut history = History::default();
+        record(&mut history, &mut lists, insert(1, "Build"));
+        record(&mut history, &mut lists, insert(0, "Plan"));
+
+        history.undo(&mut lists).unwrap();
+        history.undo(&mut lists).unwrap();
+        assert_eq!(descriptions(&lists), ["Design"]);
+        assert!(history.undo(&mut lists).is_err());
+
+        history.redo(&mut lists).unwrap();
+        assert_eq!(descriptions(&lists), ["Design", "Build"]);
+
+        // A new change drops what was left to redo
+        record(&mut history, &mut lists, insert(2, "Ship"));
+        assert_eq!(history.redo(&mut lists).unwrap_err(), "Nothing to redo");
+    }
+
+    #[test]
+    fn batch_is_undone_as_one() {
+        let mut lists = lists(&["Design", "Build"]);
+        let mut history = History::default();
+        let remove = Operation::Remove {
+            list: "inbox".to_string(),
+            path: TaskPath::top(0),
+            task: lists[0].tasks[0].clone(),
+        };
+        let batch = Operation::Batch {
+            operations: vec![remove, insert(1, "Ship")],
+        };
+        record(&mut history, &mut lists, batch);
+        assert_eq!(descriptions(&lists), ["Build", "Ship"]);
+
+        let undone = history.undo(&mut lists).unwrap();
+        assert_eq!(descriptions(&lists), ["Design", "Build"]);
+        assert_eq!(undone.describe(), "delete \"Design\" and add \"Ship\"");
+    }
+
+    #[test]
+    fn failing_batch_changes_nothing() {
+        let mut lists = lists(&["Design"]);
+        let batch = Operation::Batch {
+            operations: vec![insert(1, "Build"), insert(5, "Ship")],
+        };
+
+        assert!(batch.apply(&mut lists).is_err());
+        assert_eq!(descriptions(&lists), ["Design"]);
+    }
+
+    #[test]
+    fn undo_refuses_a_task_changed_elsewhere() {
+        let mut lists = lists(&["Design"]);
+        let mut history = History::default();
+        record(&mut history, &mut lists, insert(1, "Build"));
+        // A sync replaced the task at that path with another one
+        lists[0].tasks[1] = task("Build");
+
+        assert!(history.undo(&mut lists).is_err());
+        assert_eq!(descriptions(&lists), ["Design", "Build"]);
+        // The step stays on the stack for another try
+        assert_eq!(history.undo.len(), 1);
+    }
+
+    #[test]
+    fn keeps_the_latest_changes() {
+        let mut lists = lists(&[]);
+        let mut history = History::default();
+        for position in 0..HISTORY_LIMIT + 5 {
+            record(&mut history, &mut lists, insert(position, "Task"));
+        }
+
+        assert_eq!(history.undo.len(), HISTORY_LIMIT);
+        for _ in 0..HISTORY_LIMIT {
+            history.undo(&mut lists).unwrap();
+        }
+        assert_eq!(lists[0].tasks.len(), 5);
+    }
+
+    #[test]
+    fn long_batch_is_described_by_its_first_step() {
+        let batch = Operation::Batch {
+            operations: (0..5).map(|position| insert(position, "Task")).collect(),
+        };
+
+        assert_eq!(batch.describe(), "add \"Task\" and 4 more changes");
+    }
+}
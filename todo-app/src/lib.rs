@@ -0,0 +1,24 @@
+//! The task model behind the `todo` binary: tasks, lists, storage and
+//! `TodoApp`, which changes and queries them. Nothing in here reads from
+//! the terminal or prints, so the command line, the menu, the full-screen
+//! mode and other front-ends all drive the same methods and get results back.
+
+mod app;
+pub mod archive;
+pub mod bulk;
+pub mod dates;
+pub mod dependencies;
+pub mod formats;
+pub mod history;
+pub mod lists;
+mod merge;
+pub mod query;
+pub mod recurrence;
+pub mod stats;
+pub mod storage;
+pub mod task;
+pub mod timelog;
+pub mod tree;
+pub mod workflow;
+
+pub use app::{TodoApp, TodoError};
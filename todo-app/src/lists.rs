@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+use crate::task::Task;
+
+/// The list new data files start with, and old single-list files are moved into.
+pub const DEFAULT_LIST: &str = "inbox";
+
+/// A named list of tasks, e.g. one per project.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskList {
+    pub name: String,
+    #[serde(default)]
+    pub tasks: Vec<Task>,
+    /// Completed tasks moved out of the list, in the order they were archived.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub archive: Vec<Task>,
+}
+
+impl TaskList {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            tasks: Vec::new(),
+            archive: Vec::new(),
+        }
+    }
+}
+
+pub fn position(lists: &[TaskList], name: &str) -> Option<usize> {
+    lists.iter().position(|list| list.name == name)
+}
+
+pub fn tasks_mut<'a>(lists: &'a mut [TaskList], name: &str) -> Option<&'a mut Vec<Task>> {
+    lists
+        .iter_mut()
+        .find(|list| list.name == name)
+        .map(|list| &mut list.tasks)
+}
+
+pub fn archive_mut<'a>(lists: &'a mut [TaskList], name: &str) -> Option<&'a mut Vec<Task>> {
+    lists
+        .iter_mut()
+        .find(|list| list.name == name)
+        .map(|list| &mut list.archive)
+}
+
+/// List names are single words so they can be typed in commands and filters.
+pub fn validate_name(lists: &[TaskList], name: &str) -> Result<(), String> {
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(format!("Invalid list name: \"{name}\" (use a single word)"));
+    }
+
+    if position(lists, name).is_some() {
+        return Err(format!("A list named {name} already exists"));
+    }
+
+    Ok(())
+}
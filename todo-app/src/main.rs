@@ -1,174 +1,65 @@
+mod board;
+mod cli;
+mod editor;
+mod menu;
+mod output;
+mod passphrase;
+mod reminders;
+mod server;
+mod ui;
+
+use clap::Parser;
 use colored::*;
-use std::io;
+use std::process::ExitCode;
 
-const COMPLETED_STRING: &str = "[✓]";
-const NOT_COMPLETED_STRING: &str = "[]";
+use cli::Cli;
+use todo_core::{storage, TodoApp};
 
-enum Choice {
-    Add,
-    List,
-    Delete,
-    MarkAsDone,
-}
-
-#[derive(Debug, Clone)]
-struct Task {
-    description: String,
-    is_completed: bool,
-}
-struct TodoApp {
-    items: Vec<Task>,
-}
-
-impl TodoApp {
-    fn new() -> Self {
-        Self { items: Vec::new() }
-    }
+fn main() -> ExitCode {
+    let cli = Cli::parse();
 
-    fn handle_choice(&mut self, choice: Choice) {
-        match choice {
-            Choice::Add => Self::add_item(self),
-            Choice::List => Self::list_items(self),
-            Choice::Delete => Self::delete_item(self),
-            Choice::MarkAsDone => Self::mark_as_done(self),
+    let mut store = match storage::open(cli.file, cli.store) {
+        Ok(store) => store,
+        Err(err) => {
+            eprintln!("{}", format!("Failed to open data file: {err}").red());
+            return ExitCode::from(cli::EXIT_FAILURE);
         }
+    };
+    let path = store.path().to_path_buf();
+
+    if let Err(err) = passphrase::unlock(store.as_mut()) {
+        eprintln!(
+            "{}",
+            format!("Failed to unlock {}: {}", path.display(), err).red()
+        );
+        return ExitCode::from(cli::EXIT_FAILURE);
     }
 
-    fn add_item(&mut self) {
-        clear_screen();
-
-        println!("{}", "Please enter description".blue());
-        let name: String = get_name();
-
-        self.items.push(Task {
-            description: name,
-            is_completed: false,
-        });
-        self.list_items();
-    }
-
-    fn delete_item(&mut self) {
-        clear_screen();
-
-        if self.items.is_empty() {
-            println!("Please add item first");
-            return;
-        };
-
-        println!("{}", "Please enter index of task".blue());
-
-        if let Some(index) = self.get_item_index() {
-            let removed_task = self.items.remove(index);
-            println!("Deleted task: {:?}", removed_task);
+    let mut todo_app = match TodoApp::new(store, cli.timezone.unwrap_or_default()) {
+        Ok(app) => app,
+        Err(err) => {
+            eprintln!(
+                "{}",
+                format!("Failed to load {}: {}", path.display(), err).red()
+            );
+            return ExitCode::from(cli::EXIT_FAILURE);
         }
-    }
-
-    fn list_items(&mut self) {
-        println!("\n{}", "=== YOUR TASKS ===");
+    };
 
-        if self.items.is_empty() {
-            println!("No tasks available")
-        } else {
-            for (index, task) in self.items.iter().enumerate() {
-                let status = if task.is_completed {
-                    COMPLETED_STRING
-                } else {
-                    NOT_COMPLETED_STRING
-                };
+    todo_app.set_archive_after(cli.archive_after);
 
-                println!("{}: {} {}", index + 1, status, task.description);
-            }
+    if let Some(name) = &cli.list {
+        if let Err(err) = todo_app.select(name) {
+            eprintln!("{}", err.to_string().red());
+            return ExitCode::from(cli::EXIT_NOT_FOUND);
         }
-
-        println!("==================\n");
     }
 
-    fn mark_as_done(&mut self) {
-        clear_screen();
-        self.list_items();
-
-        println!("{}", "Please enter index of task".blue());
-
-        if let Some(index) = self.get_item_index() {
-            let mut updated_task = self.items[index].clone();
-
-            updated_task.description.red().dimmed();
-            updated_task.is_completed = true;
-
-            self.items[index] = updated_task;
-
-            println!("Task marked as done!");
-        }
-    }
-
-    fn get_item_index(&self) -> Option<usize> {
-        let input = get_name();
-        return match input.trim().parse::<usize>() {
-            Ok(num) if num > 0 && num <= self.items.len() => Some(num - 1),
-            _ => {
-                println!("{}", "Please enter a valid index".red());
-                return None;
-            }
-        };
-    }
-}
-
-impl Choice {
-    fn from_u8(num: u8) -> Option<Choice> {
-        match num {
-            1 => Some(Choice::List),
-            2 => Some(Choice::Add),
-            3 => Some(Choice::Delete),
-            4 => Some(Choice::MarkAsDone),
-            _ => None,
-        }
-    }
-}
-
-fn get_name() -> String {
-    let mut input = String::new();
-
-    io::stdin()
-        .read_line(&mut input)
-        .expect("Failed to read name");
-
-    return input.trim().to_string();
-}
-
-fn clear_screen() {
-    print!("\x1B[2J\x1B[1;1H"); // ANSI escape code to clear screen
-}
-
-fn main() {
-    println!("{}\n\n\n", "Welcome to Todo App!!".yellow());
-
-    let mut todo_app = TodoApp::new();
-
-    loop {
-        println!("Please enter a choice: \n1.List\t2.Add\t3.Delete\t4.Mark as done\t5.Exit\t\n");
-
-        let mut input = String::new();
-
-        io::stdin()
-            .read_line(&mut input)
-            .expect("Failed to read command");
-
-        let choice: u8 = match input.trim().parse() {
-            Ok(num) => num,
-            Err(_) => {
-                println!("Invalid choice. Please enter a number between 1 and 4");
-                return;
-            }
-        };
-
-        if choice == 5 {
-            println!("Exiting todo App. Goodbye!");
-            break;
-        }
-
-        match Choice::from_u8(choice) {
-            Some(valid_choice) => todo_app.handle_choice(valid_choice),
-            None => println!("Invalid choice. Please enter a valid choice"),
+    match cli.command {
+        Some(command) => cli::run(todo_app, command),
+        None => {
+            menu::run(todo_app);
+            ExitCode::SUCCESS
         }
     }
 }
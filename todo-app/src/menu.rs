@@ -0,0 +1,757 @@
+//! The interactive menu that starts when `todo` runs without a subcommand.
+
+use chrono::Local;
+use colored::*;
+use std::io;
+
+use crate::{editor, output};
+use todo_core::{
+    archive::Retention,
+    bulk::{BulkAction, Selection},
+    query::{Query, SortKey},
+    stats::Stats,
+    timelog::Effort,
+    tree::{TaskPath, TaskRef},
+    TodoApp, TodoError,
+};
+
+/// Keys that leave the menu.
+const EXIT_KEYS: [&str; 2] = ["0", "q"];
+
+enum Choice {
+    Add,
+    List,
+    Delete,
+    MarkAsDone,
+    Edit,
+    Filter,
+    Undo,
+    Redo,
+    Lists,
+    Time,
+    Dependencies,
+    Board,
+    Bulk,
+    Archive,
+    Details,
+}
+
+struct Menu {
+    todo_app: TodoApp,
+}
+
+impl Menu {
+    fn handle_choice(&mut self, choice: Choice) {
+        match choice {
+            Choice::Add => self.add_item(),
+            Choice::List => output::list_items(&self.todo_app),
+            Choice::Delete => self.delete_item(),
+            Choice::MarkAsDone => self.mark_as_done(),
+            Choice::Edit => self.edit_item(),
+            Choice::Filter => self.filter_items(),
+            Choice::Undo => self.undo_last(),
+            Choice::Redo => self.redo_last(),
+            Choice::Lists => self.manage_lists(),
+            Choice::Time => self.track_time(),
+            Choice::Dependencies => self.manage_dependencies(),
+            Choice::Board => self.manage_board(),
+            Choice::Bulk => self.bulk_change(),
+            Choice::Archive => self.manage_archive(),
+            Choice::Details => self.show_details(),
+        }
+    }
+
+    fn add_item(&mut self) {
+        clear_screen();
+
+        let parent = if self.todo_app.items().is_empty() {
+            None
+        } else {
+            output::list_items(&self.todo_app);
+            println!(
+                "{}",
+                "Please enter index or id of parent task (leave empty for a top-level task)".blue()
+            );
+
+            match get_name().as_str() {
+                "" => None,
+                input => match self.parse_index(input) {
+                    Some(path) => Some(path),
+                    None => return,
+                },
+            }
+        };
+
+        println!("{}", "Please enter description".blue());
+        let name: String = get_name();
+
+        if let Err(err) = self.todo_app.add(&name, parent.as_ref()) {
+            println!("{}", err.to_string().red());
+        }
+        output::list_items(&self.todo_app);
+    }
+
+    fn delete_item(&mut self) {
+        clear_screen();
+
+        if self.todo_app.items().is_empty() {
+            println!("Please add item first");
+            return;
+        };
+
+        println!(
+            "{}",
+            "Please enter index or id of task, or several like 1-4,7".blue()
+        );
+
+        let input = get_name();
+        if input.parse::<TaskRef>().is_err() {
+            self.apply_bulk(&input, BulkAction::Delete);
+            return;
+        }
+
+        if let Some(path) = self.parse_index(&input) {
+            match self.todo_app.remove(&path) {
+                Ok(removed_task) => println!("Deleted task: {}", removed_task.description),
+                Err(err) => println!("{}", err.to_string().red()),
+            }
+        }
+    }
+
+    fn manage_lists(&mut self) {
+        clear_screen();
+        output::show_lists(&self.todo_app);
+
+        println!(
+            "Please enter a choice: \n1.Switch\t2.New\t3.Rename\t4.Delete\t5.Move task\t6.Show all\t7.Back\t\n"
+        );
+
+        let result = match get_name().as_str() {
+            "1" => {
+                println!("{}", "Please enter name of list".blue());
+                let name = get_name();
+                self.todo_app
+                    .switch_list(&name)
+                    .map(|_| format!("Switched to list {name}"))
+            }
+            "2" => {
+                println!("{}", "Please enter name of new list".blue());
+                let name = get_name();
+                self.todo_app
+                    .create_list(&name)
+                    .map(|_| format!("Created list {name}"))
+            }
+            "3" => {
+                println!("{}", "Please enter name of list".blue());
+                let from = get_name();
+                println!("{}", "Please enter new name".blue());
+                let to = get_name();
+                self.todo_app
+                    .rename_list(&from, &to)
+                    .map(|_| format!("Renamed list {from} to {to}"))
+            }
+            "4" => {
+                println!("{}", "Please enter name of list".blue());
+                let name = get_name();
+                self.todo_app.delete_list(&name).map(|list| {
+                    format!("Deleted list {} with {} tasks", list.name, list.tasks.len())
+                })
+            }
+            "5" => {
+                output::list_items(&self.todo_app);
+                println!("{}", "Please enter index or id of task".blue());
+                let Some(path) = self.get_item_index() else {
+                    return;
+                };
+                println!("{}", "Please enter name of target list".blue());
+                let to = get_name();
+                self.todo_app
+                    .move_task(&path, &to)
+                    .map(|destination| format!("Moved task to {to} as {destination}"))
+            }
+            "6" => {
+                output::list_all(&self.todo_app, &Query::default());
+                return;
+            }
+            _ => return,
+        };
+
+        match result {
+            Ok(msg) => println!("{msg}"),
+            Err(err) => println!("{}", err.to_string().red()),
+        }
+    }
+
+    fn filter_items(&mut self) {
+        clear_screen();
+
+        println!(
+            "{}",
+            "Please enter a filter, e.g. status:open +ops due:week (leave empty for all)".blue()
+        );
+        let filter = get_name();
+
+        println!(
+            "{}",
+            "Sort by due, priority or created (leave empty to keep order)".blue()
+        );
+        let sort = get_name();
+
+        let sort = match sort.as_str() {
+            "" => None,
+            key => match key.parse::<SortKey>() {
+                Ok(key) => Some(key),
+                Err(err) => {
+                    println!("{}", err.red());
+                    return;
+                }
+            },
+        };
+
+        match Query::parse(&filter, sort) {
+            Ok(query) => output::list_matching(&self.todo_app, &query),
+            Err(err) => println!("{}", err.red()),
+        }
+    }
+
+    fn mark_as_done(&mut self) {
+        clear_screen();
+        output::list_items(&self.todo_app);
+
+        println!(
+            "{}",
+            "Please enter index or id of task, or several like 1-4,7".blue()
+        );
+
+        let input = get_name();
+        if input.parse::<TaskRef>().is_err() {
+            self.apply_bulk(&input, BulkAction::Complete);
+            return;
+        }
+
+        if let Some(path) = self.parse_index(&input) {
+            let result = match self.todo_app.complete(&path, false) {
+                Err(TodoError::Blocked(msg)) => {
+                    println!("{}", msg.yellow());
+                    println!("{}", "Mark it as done anyway? (y/N)".blue());
+
+                    if get_name().eq_ignore_ascii_case("y") {
+                        self.todo_app.complete(&path, true)
+                    } else {
+                        return;
+                    }
+                }
+                result => result,
+            };
+
+            match result {
+                Ok(()) => println!("Task marked as done!"),
+                Err(err) => println!("{}", err.to_string().red()),
+            }
+        }
+    }
+
+    fn edit_item(&mut self) {
+        clear_screen();
+        output::list_items(&self.todo_app);
+
+        println!("{}", "Please enter index or id of task".blue());
+
+        if let Some(path) = self.get_item_index() {
+            println!("{}", "Please enter new description".blue());
+            let name: String = get_name();
+
+            match self.todo_app.edit(&path, &name) {
+                Ok(()) => println!("Task updated!"),
+                Err(err) => println!("{}", err.to_string().red()),
+            }
+        }
+    }
+
+    fn undo_last(&mut self) {
+        clear_screen();
+
+        match self.todo_app.undo() {
+            Ok(operation) => println!("Undid {}", operation.describe()),
+            Err(err) => println!("{}", err.to_string().red()),
+        }
+        output::list_items(&self.todo_app);
+    }
+
+    fn redo_last(&mut self) {
+        clear_screen();
+
+        match self.todo_app.redo() {
+            Ok(operation) => println!("Redid {}", operation.describe()),
+            Err(err) => println!("{}", err.to_string().red()),
+        }
+        output::list_items(&self.todo_app);
+    }
+
+    fn track_time(&mut self) {
+        clear_screen();
+        output::list_items(&self.todo_app);
+
+        println!(
+            "Please enter a choice: \n1.Start timer\t2.Stop timer\t3.Log time\t4.Report\t5.Stats\t6.Back\t\n"
+        );
+
+        let result = match get_name().as_str() {
+            "1" => {
+                println!("{}", "Please enter index or id of task".blue());
+                let Some(path) = self.get_item_index() else {
+                    return;
+                };
+                self.todo_app
+                    .start_timer(&path)
+                    .map(|_| format!("Started timer of task {path}"))
+            }
+            "2" => self
+                .todo_app
+                .stop_timer(None)
+                .map(|count| format!("Stopped {count} timer(s)")),
+            "3" => {
+                println!("{}", "Please enter index or id of task".blue());
+                let Some(path) = self.get_item_index() else {
+                    return;
+                };
+                println!("{}", "Please enter time spent, e.g. 45m or 1h30m".blue());
+                match get_name().parse::<Effort>() {
+                    Ok(effort) => self
+                        .todo_app
+                        .log_work(&path, effort, None)
+                        .map(|_| format!("Logged {effort} on task {path}")),
+                    Err(err) => Err(TodoError::InvalidInput(err)),
+                }
+            }
+            "4" => {
+                let today = Local::now().date_naive();
+                output::print_report(&self.todo_app, today - chrono::Days::new(6), today);
+                return;
+            }
+            "5" => {
+                let today = self.todo_app.now().date();
+                output::print_stats(
+                    &self
+                        .todo_app
+                        .stats(Stats::default_from(today), today, false),
+                );
+                return;
+            }
+            _ => return,
+        };
+
+        match result {
+            Ok(msg) => println!("{msg}"),
+            Err(err) => println!("{}", err.to_string().red()),
+        }
+    }
+
+    fn manage_board(&mut self) {
+        clear_screen();
+        output::print_board(&self.todo_app, false);
+
+        println!(
+            "{}",
+            "Please enter index or id of a task to move (leave empty to go back)".blue()
+        );
+        let input = get_name();
+        if input.is_empty() {
+            return;
+        }
+        let Some(path) = self.parse_index(&input) else {
+            return;
+        };
+
+        println!(
+            "{}",
+            format!(
+                "Please enter its new state ({})",
+                self.todo_app.workflow().states().join(", ")
+            )
+            .blue()
+        );
+        let state = get_name();
+
+        match self.todo_app.set_state(&path, &state, false) {
+            Ok(()) => output::print_board(&self.todo_app, false),
+            Err(err) => println!("{}", err.to_string().red()),
+        }
+    }
+
+    fn manage_dependencies(&mut self) {
+        clear_screen();
+        output::list_items(&self.todo_app);
+
+        println!(
+            "Please enter a choice: \n1.Add dependency\t2.Remove dependency\t3.Next actions\t4.Back\t\n"
+        );
+
+        let add = match get_name().as_str() {
+            "1" => true,
+            "2" => false,
+            "3" => {
+                output::list_actionable(&self.todo_app, false);
+                return;
+            }
+            _ => return,
+        };
+
+        println!("{}", "Please enter index or id of the waiting task".blue());
+        let Some(path) = self.get_item_index() else {
+            return;
+        };
+        println!(
+            "{}",
+            "Please enter index or id of the task it waits for".blue()
+        );
+        let Some(on) = self.get_task_ref() else {
+            return;
+        };
+
+        let result = if add {
+            self.todo_app
+                .add_dependencies(&path, std::slice::from_ref(&on))
+                .map(|_| format!("Task {path} now waits for task {on}"))
+        } else {
+            self.todo_app
+                .remove_dependencies(&path, std::slice::from_ref(&on))
+                .map(|_| format!("Task {path} no longer waits for task {on}"))
+        };
+
+        match result {
+            Ok(msg) => println!("{msg}"),
+            Err(err) => println!("{}", err.to_string().red()),
+        }
+    }
+
+    fn bulk_change(&mut self) {
+        clear_screen();
+        output::list_items(&self.todo_app);
+
+        println!(
+            "Please enter a choice: \n1.Complete\t2.Reopen\t3.Delete\t4.Tag\t5.Priority\t6.Move\t7.Back\t\n"
+        );
+
+        let action = match get_name().as_str() {
+            "1" => Ok(BulkAction::Complete),
+            "2" => Ok(BulkAction::Reopen),
+            "3" => Ok(BulkAction::Delete),
+            "4" => {
+                println!(
+                    "{}",
+                    "Please enter tags to add or remove, e.g. +ops -backend".blue()
+                );
+                BulkAction::retag(&[get_name()])
+            }
+            "5" => {
+                println!("{}", "Please enter high, medium, low or none".blue());
+                BulkAction::prioritize(&get_name())
+            }
+            "6" => {
+                println!("{}", "Please enter the list to move to".blue());
+                Ok(BulkAction::Move(get_name()))
+            }
+            _ => return,
+        };
+        let action = match action {
+            Ok(action) => action,
+            Err(err) => {
+                println!("{}", err.red());
+                return;
+            }
+        };
+
+        println!(
+            "{}",
+            "Please enter tasks like 1-4,7,#3fa9 or a filter like status:done +ops".blue()
+        );
+        let input = get_name();
+        self.apply_bulk(&input, action);
+    }
+
+    /// Shows the tasks `input` selects and applies `action` to all of them
+    /// once confirmed.
+    fn apply_bulk(&mut self, input: &str, action: BulkAction) {
+        let picked = Selection::parse(input, self.todo_app.workflow())
+            .map_err(TodoError::InvalidInput)
+            .and_then(|selection| self.todo_app.pick(&selection));
+        let picked = match picked {
+            Ok(picked) if picked.is_empty() => {
+                println!("No tasks match");
+                return;
+            }
+            Ok(picked) => picked,
+            Err(err) => {
+                println!("{}", err.to_string().red());
+                return;
+            }
+        };
+
+        output::print_preview(&self.todo_app, &picked, &action);
+        let ids: Vec<String> = picked.iter().map(|entry| entry.task.id.clone()).collect();
+
+        println!("{}", "Apply? (y/N)".blue());
+        if !get_name().eq_ignore_ascii_case("y") {
+            return;
+        }
+
+        let result = match self.todo_app.bulk(&ids, &action, false) {
+            Err(TodoError::Blocked(msg)) => {
+                println!("{}", msg.yellow());
+                println!("{}", "Mark them as done anyway? (y/N)".blue());
+
+                if get_name().eq_ignore_ascii_case("y") {
+                    self.todo_app.bulk(&ids, &action, true)
+                } else {
+                    return;
+                }
+            }
+            result => result,
+        };
+
+        match result {
+            Ok(count) => println!("Changed {count} task(s)"),
+            Err(err) => println!("{}", err.to_string().red()),
+        }
+    }
+
+    fn manage_archive(&mut self) {
+        clear_screen();
+        output::list_archived(&self.todo_app, false, &Query::default());
+
+        println!(
+            "Please enter a choice: \n1.Archive done tasks\t2.Archive a task\t3.Restore\t4.Purge\t5.Back\t\n"
+        );
+
+        let result = match get_name().as_str() {
+            "1" => self
+                .todo_app
+                .archive_completed(false, None)
+                .map(|count| format!("Archived {count} task(s)")),
+            "2" => {
+                output::list_items(&self.todo_app);
+                println!("{}", "Please enter index or id of a done task".blue());
+                let Some(path) = self.get_item_index() else {
+                    return;
+                };
+                self.todo_app
+                    .archive(&path)
+                    .map(|_| format!("Archived task {path}"))
+            }
+            "3" => {
+                println!("{}", "Please enter index or id of an archived task".blue());
+                let task = match get_name().parse::<TaskRef>() {
+                    Ok(task) => task,
+                    Err(err) => {
+                        println!("{}", err.red());
+                        return;
+                    }
+                };
+                self.todo_app
+                    .restore(&task)
+                    .map(|path| format!("Restored task as {path}"))
+            }
+            "4" => {
+                println!(
+                    "{}",
+                    "Purge tasks done how many days ago or earlier? (leave empty for all)".blue()
+                );
+                let input = get_name();
+                let days = match input.as_str() {
+                    "" => None,
+                    input => match input.parse() {
+                        Ok(days) => Some(days),
+                        Err(_) => {
+                            println!("{}", "Please enter a number of days".red());
+                            return;
+                        }
+                    },
+                };
+                let retention = Retention { days, keep: None };
+
+                let expired = self.todo_app.expired(&retention, false);
+                if expired.is_empty() {
+                    println!("No archived tasks to purge");
+                    return;
+                }
+                output::print_expired(&self.todo_app, &expired);
+
+                println!("{}", "Apply? (y/N)".blue());
+                if !get_name().eq_ignore_ascii_case("y") {
+                    return;
+                }
+                self.todo_app
+                    .purge(&retention, false)
+                    .map(|count| format!("Purged {count} task(s)"))
+            }
+            _ => return,
+        };
+
+        match result {
+            Ok(msg) => println!("{msg}"),
+            Err(err) => println!("{}", err.to_string().red()),
+        }
+    }
+
+    fn show_details(&mut self) {
+        clear_screen();
+        output::list_items(&self.todo_app);
+
+        println!("{}", "Please enter index or id of task".blue());
+        let Some(path) = self.get_item_index() else {
+            return;
+        };
+        match self.todo_app.task(&path) {
+            Ok(task) => output::print_details(&self.todo_app, &path, task),
+            Err(err) => {
+                println!("{}", err.to_string().red());
+                return;
+            }
+        }
+
+        println!("Please enter a choice: \n1.Edit note\t2.Attach\t3.Remove attachment\t4.Back\t\n");
+
+        let result = match get_name().as_str() {
+            "1" => self
+                .todo_app
+                .task(&path)
+                .and_then(|task| editor::edit(&task.note))
+                .and_then(|note| self.todo_app.set_note(&path, &note))
+                .map(|changed| {
+                    if changed {
+                        "Note saved".to_string()
+                    } else {
+                        "The note did not change".to_string()
+                    }
+                }),
+            "2" => {
+                println!("{}", "Please enter a file path or URL".blue());
+                self.todo_app
+                    .attach(&path, &[get_name()])
+                    .map(|attached| format!("Attached {}", attached.join(", ")))
+            }
+            "3" => {
+                println!(
+                    "{}",
+                    "Please enter the number, URL or path of the attachment".blue()
+                );
+                self.todo_app
+                    .detach(&path, &[get_name()])
+                    .map(|removed| format!("Removed {}", removed.join(", ")))
+            }
+            _ => return,
+        };
+
+        match result {
+            Ok(msg) => println!("{msg}"),
+            Err(err) => println!("{}", err.to_string().red()),
+        }
+    }
+
+    fn get_item_index(&mut self) -> Option<TaskPath> {
+        let input = get_name();
+        self.parse_index(&input)
+    }
+
+    /// Reads an index such as `2` or `2.1`, or an id such as `#3fa9`, pointing
+    /// at an existing task. A task in another list makes that list the current one.
+    fn parse_index(&mut self, input: &str) -> Option<TaskPath> {
+        let result = input
+            .parse::<TaskRef>()
+            .map_err(TodoError::InvalidInput)
+            .and_then(|task| self.todo_app.resolve(&task));
+
+        match result {
+            Ok(path) => Some(path),
+            Err(err) => {
+                println!("{}", err.to_string().red());
+                None
+            }
+        }
+    }
+
+    /// Reads an index or id like `parse_index`, staying on the current list.
+    fn get_task_ref(&self) -> Option<TaskRef> {
+        let input = get_name();
+        match input.parse::<TaskRef>() {
+            Ok(task) if self.todo_app.locate(&task).is_ok() => Some(task),
+            _ => {
+                println!("{}", "Please enter a valid index or id".red());
+                None
+            }
+        }
+    }
+}
+
+impl Choice {
+    fn from_u8(num: u8) -> Option<Choice> {
+        match num {
+            1 => Some(Choice::List),
+            2 => Some(Choice::Add),
+            3 => Some(Choice::Delete),
+            4 => Some(Choice::MarkAsDone),
+            5 => Some(Choice::Edit),
+            6 => Some(Choice::Filter),
+            7 => Some(Choice::Undo),
+            8 => Some(Choice::Redo),
+            9 => Some(Choice::Lists),
+            10 => Some(Choice::Time),
+            11 => Some(Choice::Dependencies),
+            12 => Some(Choice::Board),
+            13 => Some(Choice::Bulk),
+            14 => Some(Choice::Archive),
+            15 => Some(Choice::Details),
+            _ => None,
+        }
+    }
+}
+
+fn get_name() -> String {
+    let mut input = String::new();
+
+    io::stdin()
+        .read_line(&mut input)
+        .expect("Failed to read name");
+
+    input.trim().to_string()
+}
+
+fn clear_screen() {
+    print!("\x1B[2J\x1B[1;1H"); // ANSI escape code to clear screen
+}
+
+pub fn run(todo_app: TodoApp) {
+    let mut menu = Menu { todo_app };
+    println!("{}\n\n\n", "Welcome to Todo App!!".yellow());
+
+    loop {
+        println!(
+            "Please enter a choice: \n1.List\t2.Add\t3.Delete\t4.Mark as done\t5.Edit\t6.Filter\t7.Undo\t8.Redo\t9.Lists\t10.Time\t11.Dependencies\t12.Board\t13.Bulk\t14.Archive\t15.Details\t0.Exit\t\n"
+        );
+
+        let mut input = String::new();
+
+        io::stdin()
+            .read_line(&mut input)
+            .expect("Failed to read command");
+
+        if EXIT_KEYS.contains(&input.trim().to_lowercase().as_str()) {
+            println!("Exiting todo App. Goodbye!");
+            break;
+        }
+
+        let choice: u8 = match input.trim().parse() {
+            Ok(num) => num,
+            Err(_) => {
+                println!("Invalid choice. Please enter a number between 1 and 15, or 0 to exit");
+                return;
+            }
+        };
+
+        match Choice::from_u8(choice) {
+            Some(valid_choice) => menu.handle_choice(valid_choice),
+            None => println!("Invalid choice. Please enter a valid choice"),
+        }
+    }
+}
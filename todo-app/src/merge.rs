@@ -0,0 +1,563 @@
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    lists::TaskList,
+    task::Task,
+    tree::{self, TaskPath},
+};
+
+/// Fields holding a set of values, merged item by item so both sides'
+/// additions are kept.
+const SET_FIELDS: &[&str] = &["tags", "depends_on", "attachments"];
+
+/// The result of a three-way merge of task lists.
+pub struct Merge {
+    pub lists: Vec<TaskList>,
+    /// Tasks changed differently on both sides, where our change was kept.
+    pub conflicts: Vec<String>,
+}
+
+/// A task without its subtasks, and where it sits.
+#[derive(Clone, PartialEq)]
+struct Record {
+    list: String,
+    parent: Option<String>,
+    /// Whether this is a top-level task in the archive of its list.
+    archived: bool,
+    fields: Map<String, Value>,
+}
+
+/// The tasks of one parent: a list or its archive, or a task.
+type Group = (String, Option<String>, bool);
+
+/// One version of the lists, with every task by key.
+struct Side {
+    names: Vec<String>,
+    records: HashMap<String, Record>,
+    /// Keys of every task, parents before their subtasks.
+    order: Vec<String>,
+}
+
+impl Side {
+    fn new(lists: &[TaskList]) -> Self {
+        let mut side = Side {
+            names: lists.iter().map(|list| list.name.clone()).collect(),
+            records: HashMap::new(),
+            order: Vec::new(),
+        };
+
+        for (list, archived) in lists.iter().flat_map(|list| [(list, false), (list, true)]) {
+            let mut keys: HashMap<TaskPath, String> = HashMap::new();
+            let tasks = if archived { &list.archive } else { &list.tasks };
+
+            for (path, task) in tree::flatten(tasks) {
+                let key = key(&list.name, archived, &path, task);
+                let parent = path.parent().and_then(|parent| keys.get(&parent).cloned());
+                let mut fields = match serde_json::to_value(Task {
+                    subtasks: Vec::new(),
+                    ..task.clone()
+                }) {
+                    Ok(Value::Object(fields)) => fields,
+                    _ => continue,
+                };
+                fields.remove("subtasks");
+
+                keys.insert(path, key.clone());
+                side.order.push(key.clone());
+                side.records.insert(
+                    key,
+                    Record {
+                        list: list.name.clone(),
+                        archived: archived && parent.is_none(),
+                        parent,
+                        fields,
+                    },
+                );
+            }
+        }
+
+        side
+    }
+}
+
+/// Tasks are matched by id. The few stored before tasks had ids are matched
+/// by where they sit instead.
+fn key(list: &str, archived: bool, path: &TaskPath, task: &Task) -> String {
+    if task.id.is_empty() && archived {
+        format!("{list}/archive/{path}")
+    } else if task.id.is_empty() {
+        format!("{list}/{path}")
+    } else {
+        task.id.clone()
+    }
+}
+
+/// Combines the changes made since `base` in `ours` and in `theirs`.
+///
+/// Tasks are matched by id, so a task keeps the changes of both sides even
+/// when one of them moved it. Each field is merged on its own: a field only
+/// one side changed takes that change, and a field both changed keeps ours.
+/// Tags, dependencies and attachments are merged item by item and logged
+/// work is joined. A task deleted on one side and changed on the other is
+/// kept. Archiving a task moves it like moving it to another list does.
+pub fn merge(base: &[TaskList], ours: &[TaskList], theirs: &[TaskList]) -> Merge {
+    let (base, ours, theirs) = (Side::new(base), Side::new(ours), Side::new(theirs));
+    let mut conflicts = Vec::new();
+    let mut records = HashMap::new();
+
+    let keys = ours
+        .order
+        .iter()
+        .chain(&theirs.order)
+        .chain(&base.order)
+        .collect::<HashSet<_>>();
+
+    for key in keys {
+        let merged = match (
+            base.records.get(key),
+            ours.records.get(key),
+            theirs.records.get(key),
+        ) {
+            (base, Some(ours), Some(theirs)) => {
+                let (record, conflict) = merge_record(base, ours, theirs);
+                if conflict {
+                    conflicts.push(description(&record));
+                }
+                Some(record)
+            }
+            // Added on one side
+            (None, Some(record), None) | (None, None, Some(record)) => Some(record.clone()),
+            // Deleted on one side: gone, unless the other side changed it since
+            (Some(base), Some(record), None) | (Some(base), None, Some(record)) => {
+                (record != base).then(|| record.clone())
+            }
+            (_, None, None) => None,
+        };
+
+        if let Some(record) = merged {
+            records.insert(key.clone(), record);
+        }
+    }
+
+    detach_orphans(&mut records);
+
+    let names = list_names(&base, &ours, &theirs, &records);
+    let mut children = HashMap::new();
+    for key in records.keys() {
+        let record = &records[key];
+        children
+            .entry((record.list.clone(), record.parent.clone(), record.archived))
+            .or_insert_with(Vec::new)
+            .push(key.clone());
+    }
+    for (group, keys) in &mut children {
+        *keys = order(keys, group, &ours, &theirs, &records);
+    }
+
+    let lists = names
+        .into_iter()
+        .map(|name| {
+            let mut tasks = build(&mut children, &records, (name.clone(), None, false));
+            tree::sync_all(&mut tasks);
+            let mut archive = build(&mut children, &records, (name.clone(), None, true));
+            tree::sync_all(&mut archive);
+            TaskList {
+                name,
+                tasks,
+                archive,
+            }
+        })
+        .collect();
+
+    conflicts.sort();
+    Merge { lists, conflicts }
+}
+
+fn merge_record(base: Option<&Record>, ours: &Record, theirs: &Record) -> (Record, bool) {
+    let mut conflict = false;
+
+    let location = pick(
+        base.map(|base| (&base.list, &base.parent, base.archived)),
+        (&ours.list, &ours.parent, ours.archived),
+        (&theirs.list, &theirs.parent, theirs.archived),
+        &mut conflict,
+    );
+
+    let mut fields = Map::new();
+    let names = ours
+        .fields
+        .keys()
+        .chain(theirs.fields.keys())
+        .collect::<HashSet<_>>();
+
+    for name in names {
+        let base = base.map(|base| base.fields.get(name));
+        let (ours, theirs) = (ours.fields.get(name), theirs.fields.get(name));
+
+        let value = if name == "work" {
+            Some(merge_work(ours, theirs))
+        } else if SET_FIELDS.contains(&name.as_str()) {
+            Some(merge_set(base.flatten(), ours, theirs))
+        } else {
+            pick(base, ours, theirs, &mut conflict).cloned()
+        };
+
+        match value {
+            Some(Value::Array(items)) if items.is_empty() => {}
+            Some(value) => {
+                fields.insert(name.clone(), value);
+            }
+            None => {}
+        }
+    }
+
+    let record = Record {
+        list: location.0.clone(),
+        parent: location.1.clone(),
+        archived: location.2,
+        fields,
+    };
+    (record, conflict)
+}
+
+/// The side that changed a value, or ours if both changed it differently.
+fn pick<T: PartialEq>(base: Option<T>, ours: T, theirs: T, conflict: &mut bool) -> T {
+    if ours == theirs || base.as_ref() == Some(&theirs) {
+        ours
+    } else if base.as_ref() == Some(&ours) {
+        theirs
+    } else {
+        *conflict = true;
+        ours
+    }
+}
+
+/// Keeps what either side added and drops what either side removed.
+fn merge_set(base: Option<&Value>, ours: Option<&Value>, theirs: Option<&Value>) -> Value {
+    let items =
+        |value: Option<&Value>| value.and_then(Value::as_array).cloned().unwrap_or_default();
+    let (base, ours, theirs) = (items(base), items(ours), items(theirs));
+
+    let mut merged = Vec::new();
+    for item in ours.iter().chain(&theirs) {
+        let removed = base.contains(item) && !(ours.contains(item) && theirs.contains(item));
+        if !removed && !merged.contains(item) {
+            merged.push(item.clone());
+        }
+    }
+
+    Value::Array(merged)
+}
+
+/// Joins the work logged on both sides. An interval that one side stopped
+/// and the other did not takes the stop.
+fn merge_work(ours: Option<&Value>, theirs: Option<&Value>) -> Value {
+    let mut merged: Vec<Value> = Vec::new();
+
+    for interval in [ours, theirs]
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_array)
+        .flatten()
+    {
+        let start = interval.get("start");
+        match merged.iter_mut().find(|other| other.get("start") == start) {
+            Some(other) => {
+                if other.get("end").is_none() {
+                    *other = interval.clone();
+                }
+            }
+            None => merged.push(interval.clone()),
+        }
+    }
+
+    merged.sort_by_key(|interval| interval.get("start").map(Value::to_string));
+    Value::Array(merged)
+}
+
+/// Moves tasks whose parent is gone, is in another list or is one of their
+/// own subtasks to the top of their list.
+fn detach_orphans(records: &mut HashMap<String, Record>) {
+    let keys = records.keys().cloned().collect::<Vec<_>>();
+
+    for key in keys {
+        let mut seen = HashSet::from([key.clone()]);
+        let mut current = key.clone();
+
+        let detach = loop {
+            let record = &records[&current];
+            let Some(parent) = &record.parent else {
+                break false;
+            };
+            match records.get(parent) {
+                Some(next) if next.list == record.list && seen.insert(parent.clone()) => {
+                    current = parent.clone();
+                }
+                _ => break true,
+            }
+        };
+
+        if detach {
+            if let Some(record) = records.get_mut(&current) {
+                record.parent = None;
+                record.archived = false;
+            }
+        }
+    }
+}
+
+/// Lists both sides kept, plus the ones either side added, in our order.
+/// A deleted list stays while it still has tasks.
+fn list_names(
+    base: &Side,
+    ours: &Side,
+    theirs: &Side,
+    records: &HashMap<String, Record>,
+) -> Vec<String> {
+    let used = records
+        .values()
+        .map(|record| record.list.as_str())
+        .collect::<HashSet<_>>();
+
+    let mut names: Vec<String> = Vec::new();
+    for name in ours.names.iter().chain(&theirs.names) {
+        let kept = (ours.names.contains(name) && theirs.names.contains(name))
+            || !base.names.contains(name)
+            || used.contains(name.as_str());
+        if kept && !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+
+    let mut rest = used
+        .into_iter()
+        .filter(|name| !names.iter().any(|other| other == name))
+        .map(String::from)
+        .collect::<Vec<_>>();
+    rest.sort();
+    names.extend(rest);
+
+    names
+}
+
+/// Orders the tasks of one parent as ours are, placing tasks only theirs
+/// have after the task they follow there.
+fn order(
+    keys: &[String],
+    group: &Group,
+    ours: &Side,
+    theirs: &Side,
+    records: &HashMap<String, Record>,
+) -> Vec<String> {
+    let in_group = |key: &&String| {
+        keys.contains(key)
+            && records.get(*key).is_some_and(|record| {
+                (&record.list, &record.parent, record.archived) == (&group.0, &group.1, group.2)
+            })
+    };
+
+    let mut ordered: Vec<String> = ours.order.iter().filter(in_group).cloned().collect();
+
+    let mut previous: Option<&String> = None;
+    for key in theirs.order.iter().filter(in_group) {
+        if !ordered.contains(key) {
+            let position = previous
+                .and_then(|previous| ordered.iter().position(|other| other == previous))
+                .map_or(0, |position| position + 1);
+            ordered.insert(position, key.clone());
+        }
+        previous = Some(key);
+    }
+
+    for key in keys {
+        if !ordered.contains(key) {
+            ordered.push(key.clone());
+        }
+    }
+
+    ordered
+}
+
+fn build(
+    children: &mut HashMap<Group, Vec<String>>,
+    records: &HashMap<String, Record>,
+    group: Group,
+) -> Vec<Task> {
+    let list = group.0.clone();
+
+    children
+        .remove(&group)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|key| {
+            let fields = records.get(&key)?.fields.clone();
+            let mut task: Task = serde_json::from_value(Value::Object(fields)).ok()?;
+            task.subtasks = build(children, records, (list.clone(), Some(key), false));
+            Some(task)
+        })
+        .collect()
+}
+
+fn description(record: &Record) -> String {
+    record
+        .fields
+        .get("description")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::*;
+    use crate::task::Attributes;
+
+    fn task(id: &str, inline: &str) -> Task {
+        let mut task = Task::new(Attributes::parse(inline, NaiveDateTime::default()).unwrap());
+        task.id = id.to_string();
+        task
+    }
+
+    /// The inbox with design, build and ship, and an empty work list.
+    fn base() -> Vec<TaskList> {
+        let mut inbox = TaskList::new("inbox");
+        inbox.tasks = vec![
+            task("a1", "Design +ux"),
+            task("b2", "Build"),
+            task("c3", "Ship"),
+        ];
+        vec![inbox, TaskList::new("work")]
+    }
+
+    /// Descriptions of the tasks of a list, with subtasks after a `/`.
+    fn descriptions(lists: &[TaskList], name: &str) -> Vec<String> {
+        let list = lists.iter().find(|list| list.name == name).unwrap();
+        tree::flatten(&list.tasks)
+            .into_iter()
+            .map(|(path, task)| match path.parent() {
+                Some(_) => format!("/{}", task.description),
+                None => task.description.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn changes_to_different_fields_are_both_kept() {
+        let base = base();
+        let mut ours = base.clone();
+        ours[0].tasks[1].description = "Build the api".to_string();
+        let mut theirs = base.clone();
+        theirs[0].tasks[1].complete();
+
+        let merged = merge(&base, &ours, &theirs);
+
+        let build = &merged.lists[0].tasks[1];
+        assert_eq!(build.description, "Build the api");
+        assert!(build.is_completed());
+        assert!(merged.conflicts.is_empty());
+    }
+
+    #[test]
+    fn conflicting_changes_keep_ours() {
+        let base = base();
+        let mut ours = base.clone();
+        ours[0].tasks[0].description = "Design the screens".to_string();
+        let mut theirs = base.clone();
+        theirs[0].tasks[0].description = "Design the flow".to_string();
+        theirs[0].tasks[2].description = "Ship it".to_string();
+
+        let merged = merge(&base, &ours, &theirs);
+
+        assert_eq!(
+            descriptions(&merged.lists, "inbox"),
+            ["Design the screens", "Build", "Ship it"]
+        );
+        assert_eq!(merged.conflicts, ["Design the screens"]);
+    }
+
+    #[test]
+    fn tags_are_merged_one_by_one() {
+        let base = base();
+        let mut ours = base.clone();
+        ours[0].tasks[0].tags.push("web".to_string());
+        let mut theirs = base.clone();
+        theirs[0].tasks[0].tags = vec!["mobile".to_string()];
+
+        let merged = merge(&base, &ours, &theirs);
+
+        assert_eq!(merged.lists[0].tasks[0].tags, ["web", "mobile"]);
+        assert!(merged.conflicts.is_empty());
+    }
+
+    #[test]
+    fn tasks_added_on_both_sides_follow_their_neighbours() {
+        let base = base();
+        let mut ours = base.clone();
+        ours[0].tasks.push(task("d4", "Announce"));
+        let mut theirs = base.clone();
+        theirs[0].tasks.insert(1, task("e5", "Review"));
+
+        let merged = merge(&base, &ours, &theirs);
+
+        assert_eq!(
+            descriptions(&merged.lists, "inbox"),
+            ["Design", "Review", "Build", "Ship", "Announce"]
+        );
+    }
+
+    #[test]
+    fn deleted_tasks_stay_when_changed_on_the_other_side() {
+        let base = base();
+        let mut ours = base.clone();
+        ours[0].tasks.remove(2);
+        ours[0].tasks.remove(1);
+        let mut theirs = base.clone();
+        theirs[0].tasks[1].description = "Build the api".to_string();
+
+        let merged = merge(&base, &ours, &theirs);
+
+        assert_eq!(
+            descriptions(&merged.lists, "inbox"),
+            ["Design", "Build the api"]
+        );
+    }
+
+    #[test]
+    fn moves_keep_the_changes_of_the_other_side() {
+        let base = base();
+        let mut ours = base.clone();
+        ours[0].tasks[2].description = "Ship it".to_string();
+        ours[0].tasks[0].complete();
+        let mut theirs = base.clone();
+        let ship = theirs[0].tasks.remove(2);
+        theirs[1].tasks.push(ship);
+        let design = theirs[0].tasks.remove(0);
+        theirs[0].archive.push(design);
+
+        let merged = merge(&base, &ours, &theirs);
+
+        assert_eq!(descriptions(&merged.lists, "inbox"), ["Build"]);
+        assert_eq!(descriptions(&merged.lists, "work"), ["Ship it"]);
+        assert!(merged.lists[0].archive[0].is_completed());
+    }
+
+    #[test]
+    fn subtasks_of_a_deleted_task_move_to_the_top() {
+        let base = base();
+        let mut ours = base.clone();
+        ours[0].tasks[1].subtasks.push(task("f6", "Backend"));
+        let mut theirs = base.clone();
+        theirs[0].tasks.remove(1);
+
+        let merged = merge(&base, &ours, &theirs);
+
+        // Subtasks are no change to their parent, so Build is gone
+        assert_eq!(
+            descriptions(&merged.lists, "inbox"),
+            ["Design", "Backend", "Ship"]
+        );
+    }
+}
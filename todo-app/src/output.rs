@@ -0,0 +1,478 @@
+//! Printed views of the tasks, shared by the commands and the menu.
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use colored::*;
+use std::path::Path;
+
+use crate::board::{self, Card};
+use todo_core::{
+    bulk::BulkAction,
+    dependencies::{Entry, TaskIndex},
+    query::Query,
+    stats::{self, Stats},
+    task::{self, Priority, Task},
+    timelog,
+    tree::{self, TaskPath},
+    workflow, TodoApp,
+};
+
+const COMPLETED_STRING: &str = "[✓]";
+const NOT_COMPLETED_STRING: &str = "[]";
+
+pub fn print_report(todo_app: &TodoApp, from: NaiveDate, to: NaiveDate) {
+    let report = todo_app.time_report(from, to);
+
+    println!("\n=== TIME SPENT {} TO {} ===", report.from, report.to);
+
+    if report.tasks.is_empty() {
+        println!("No time logged");
+    } else {
+        println!("{}", "By task".bold());
+        for (label, spent, estimate) in &report.tasks {
+            let estimate = estimate
+                .map(|estimate| format!(" (estimate {estimate})"))
+                .unwrap_or_default();
+            println!("{:>8}  {label}{estimate}", timelog::format_delta(*spent));
+        }
+
+        println!("{}", "By tag".bold());
+        for (tag, spent) in &report.tags {
+            println!("{:>8}  {tag}", timelog::format_delta(*spent));
+        }
+
+        println!("{}", "By day".bold());
+        for (day, spent) in &report.days {
+            println!("{:>8}  {day}", timelog::format_delta(*spent));
+        }
+
+        println!("{:>8}  total", timelog::format_delta(report.total).bold());
+    }
+
+    println!("==================\n");
+}
+
+pub fn print_stats(stats: &Stats) {
+    println!("\n=== STATS {} TO {} ===", stats.from, stats.to);
+
+    println!("{:>6}  created", stats.created);
+    println!("{:>6}  completed", stats.completed);
+    println!("{:>6}  completed after the due date", stats.completed_late);
+    println!("{:>6}  open", stats.open);
+    let overdue = format!("{:>6}  overdue", stats.overdue);
+    if stats.overdue > 0 {
+        println!("{}", overdue.red());
+    } else {
+        println!("{overdue}");
+    }
+    if let Some(average) = stats.average_to_complete {
+        println!(
+            "{:>6}  average time to complete",
+            stats::format_age(average)
+        );
+    }
+
+    println!("\n{}", "Completed per day".bold());
+    println!(
+        "{}",
+        stats::bar_chart(
+            stats
+                .per_day
+                .iter()
+                .map(|(day, count)| (day.format("%a %Y-%m-%d").to_string(), count))
+        )
+    );
+
+    println!("\n{}", "Completed per week".bold());
+    println!(
+        "{}",
+        stats::bar_chart(
+            stats
+                .per_week
+                .iter()
+                .map(|(week, count)| (week.clone(), count))
+        )
+    );
+
+    if !stats.tags.is_empty() {
+        println!("\n{}", "By tag".bold());
+        println!("{:>10} {:>6} {:>8}", "completed", "open", "overdue");
+        for (tag, counts) in &stats.tags {
+            println!(
+                "{:>10} {:>6} {:>8}  {tag}",
+                counts.completed, counts.open, counts.overdue
+            );
+        }
+    }
+
+    println!("==================\n");
+}
+
+pub fn list_actionable(todo_app: &TodoApp, all: bool) {
+    let now = todo_app.now();
+    let index = TaskIndex::new(todo_app.lists());
+
+    println!("\n=== NEXT ACTIONS ===");
+
+    for list in todo_app.lists() {
+        if !all && list.name != todo_app.current() {
+            continue;
+        }
+
+        let actionable = todo_app.actionable(&list.tasks);
+        if all && !actionable.is_empty() {
+            println!("{}", list.name.bold());
+        }
+        for (path, task) in actionable {
+            println!(
+                "{}",
+                render_task(&path, index.short_id(task), task, now, &[])
+            );
+        }
+    }
+
+    println!("==================\n");
+}
+
+pub fn list_items(todo_app: &TodoApp) {
+    list_matching(todo_app, &Query::default());
+}
+
+pub fn list_matching(todo_app: &TodoApp, query: &Query) {
+    println!("\n=== YOUR TASKS ({}) ===", todo_app.current());
+    print_tasks(todo_app, todo_app.items(), query);
+    println!("==================\n");
+}
+
+/// Lists the matching archived tasks of the current list, or of every list.
+pub fn list_archived(todo_app: &TodoApp, all: bool, query: &Query) {
+    for list in todo_app.lists() {
+        if all || list.name == todo_app.current() {
+            println!("\n=== ARCHIVE ({}) ===", list.name);
+            print_tasks(todo_app, &list.archive, query);
+        }
+    }
+    println!("==================\n");
+}
+
+/// Lists the matching tasks of every list, one section per list.
+pub fn list_all(todo_app: &TodoApp, query: &Query) {
+    for list in todo_app.lists() {
+        println!("\n=== {} ===", list.name.to_uppercase());
+        print_tasks(todo_app, &list.tasks, query);
+    }
+    println!("==================\n");
+}
+
+pub fn print_tasks(todo_app: &TodoApp, tasks: &[Task], query: &Query) {
+    let now = todo_app.now();
+    let index = TaskIndex::new(todo_app.lists());
+    let matches = query.apply(tasks, now);
+
+    if tasks.is_empty() {
+        println!("No tasks available")
+    } else if matches.is_empty() {
+        println!("No matching tasks")
+    } else {
+        for (path, task) in matches {
+            let blockers = todo_app.blocked_by(&index, task);
+            println!(
+                "{}",
+                render_task(&path, index.short_id(task), task, now, &blockers)
+            );
+        }
+    }
+}
+
+/// Shows the tasks of the current list, or of every list, in a column per state.
+pub fn print_board(todo_app: &TodoApp, all: bool) {
+    let now = todo_app.now();
+    let index = TaskIndex::new(todo_app.lists());
+
+    let cards: Vec<Card> = todo_app
+        .lists()
+        .iter()
+        .filter(|list| all || list.name == todo_app.current())
+        .flat_map(|list| {
+            tree::flatten(&list.tasks)
+                .into_iter()
+                .map(move |(path, task)| (list, path, task))
+        })
+        .map(|(list, path, task)| {
+            let color = if task.is_overdue(now) {
+                Some(Color::Red)
+            } else if !task.is_completed() && index.is_blocked(task) {
+                Some(Color::Yellow)
+            } else {
+                None
+            };
+
+            Card {
+                state: task.state.clone(),
+                label: format!(
+                    "{} #{} {}",
+                    todo_app.label(&list.name, &path),
+                    index.short_id(task),
+                    task.description
+                ),
+                color,
+            }
+        })
+        .collect();
+
+    println!();
+    println!(
+        "{}",
+        board::render(todo_app.workflow(), &cards, board::width())
+    );
+    println!();
+}
+
+pub fn show_workflow(todo_app: &TodoApp) {
+    println!("{}", todo_app.workflow().states().join(" → "));
+}
+
+pub fn show_lists(todo_app: &TodoApp) {
+    println!("\n=== YOUR LISTS ===");
+
+    for list in todo_app.lists() {
+        let open = tree::flatten(&list.tasks)
+            .iter()
+            .filter(|(_, task)| !task.is_completed())
+            .count();
+        let marker = if list.name == todo_app.current() {
+            "*"
+        } else {
+            " "
+        };
+
+        println!("{marker} {} ({open} open)", list.name);
+    }
+
+    println!("==================\n");
+}
+
+/// One line of a task listing, with its index and short id. `blocked_by`
+/// holds the labels of the open tasks it waits for.
+pub fn render_task(
+    path: &TaskPath,
+    short_id: &str,
+    task: &Task,
+    now: NaiveDateTime,
+    blocked_by: &[String],
+) -> String {
+    // States between todo and done are written out
+    let status = match task.state.as_str() {
+        workflow::DONE => COMPLETED_STRING.to_string(),
+        workflow::TODO => NOT_COMPLETED_STRING.to_string(),
+        state => format!("[{state}]"),
+    };
+    let is_overdue = task.is_overdue(now);
+    // Overdue and completed tasks are styled as a whole, so their parts stay plain.
+    let plain = is_overdue || task.is_completed();
+    let paint = |text: String, color: Color| {
+        if plain {
+            text
+        } else {
+            text.color(color).to_string()
+        }
+    };
+
+    let mut line = format!(
+        "{}{}{}: {} {}",
+        "  ".repeat(path.depth()),
+        path,
+        paint(format!(" #{short_id}"), Color::BrightBlack),
+        status,
+        task.description
+    );
+
+    if let Some(priority) = task.priority {
+        let color = match priority {
+            Priority::High => Color::Red,
+            Priority::Medium => Color::Yellow,
+            Priority::Low => Color::Blue,
+        };
+        line.push_str(&paint(format!(" !{priority}"), color));
+    }
+
+    for tag in &task.tags {
+        line.push_str(&paint(format!(" +{tag}"), Color::Cyan));
+    }
+
+    if let Some(due) = task.due {
+        line.push_str(&format!(" due:{due}"));
+    }
+
+    if let Some(recurrence) = &task.recurrence {
+        line.push_str(&paint(format!(" repeat:{recurrence}"), Color::Magenta));
+    }
+
+    // The note and attachments are shown by `todo show`
+    if !task.note.is_empty() || !task.attachments.is_empty() {
+        line.push_str(&paint(" ✎".to_string(), Color::BrightBlack));
+    }
+
+    if !blocked_by.is_empty() {
+        line.push_str(&paint(
+            format!(" (blocked by {})", blocked_by.join(", ")),
+            Color::Yellow,
+        ));
+    }
+
+    if let Some(time) = time_label(task) {
+        let color = if task.is_running() {
+            Color::Green
+        } else {
+            Color::White
+        };
+        line.push_str(&paint(format!(" {time}"), color));
+    }
+
+    if is_overdue {
+        format!("{} (overdue)", line).red().bold().to_string()
+    } else if task.is_completed() {
+        line.dimmed().to_string()
+    } else {
+        line
+    }
+}
+
+/// Time spent against the estimate, e.g. `[1h05m/2h]`, with `⏱` while the timer runs.
+pub fn time_label(task: &Task) -> Option<String> {
+    if task.work.is_empty() && task.estimate.is_none() {
+        return None;
+    }
+
+    let spent = timelog::format_delta(task.spent(Local::now()));
+    let label = match task.estimate {
+        Some(estimate) => format!("[{spent}/{estimate}]"),
+        None => format!("[{spent}]"),
+    };
+
+    Some(if task.is_running() {
+        format!("{label} ⏱")
+    } else {
+        label
+    })
+}
+
+/// Shows what a bulk action is about to do, and to which tasks. Tasks of
+/// other lists are prefixed with their list.
+pub fn print_preview(todo_app: &TodoApp, picked: &[Entry], action: &BulkAction) {
+    let index = TaskIndex::new(todo_app.lists());
+
+    println!("{}:", action.describe(picked.len()).bold());
+    for entry in picked {
+        let line = render_task(
+            &entry.path,
+            index.short_id(entry.task),
+            entry.task,
+            todo_app.now(),
+            &todo_app.blocked_by(&index, entry.task),
+        );
+
+        if entry.list == todo_app.current() {
+            println!("  {line}");
+        } else {
+            println!("  {} {line}", entry.list);
+        }
+    }
+}
+
+/// Shows the archived tasks a purge is about to delete, with their list.
+pub fn print_expired(todo_app: &TodoApp, expired: &[(&str, usize, &Task)]) {
+    let now = todo_app.now();
+    let index = TaskIndex::new(todo_app.lists());
+
+    println!(
+        "{}:",
+        format!("Purge {} archived task(s)", expired.len()).bold()
+    );
+    for (list, position, task) in expired {
+        let line = render_task(
+            &TaskPath::top(*position),
+            index.short_id(task),
+            task,
+            now,
+            &[],
+        );
+        println!("  {list} {line}");
+    }
+}
+
+/// Everything about one task of the current list, with its note and
+/// attachments. Fields the task does not use are left out.
+pub fn print_details(todo_app: &TodoApp, path: &TaskPath, task: &Task) {
+    let index = TaskIndex::new(todo_app.lists());
+    let timestamp =
+        |time: Option<DateTime<Local>>| time.map(|time| time.format("%Y-%m-%d %H:%M").to_string());
+    let done = task
+        .subtasks
+        .iter()
+        .filter(|subtask| subtask.is_completed())
+        .count();
+
+    let fields = [
+        ("State", Some(task.state.clone())),
+        (
+            "Priority",
+            task.priority.map(|priority| priority.to_string()),
+        ),
+        ("Due", task.due.map(|due| due.to_string())),
+        (
+            "Tags",
+            Some(task.tags.join(", ")).filter(|tags| !tags.is_empty()),
+        ),
+        (
+            "Repeats",
+            task.recurrence
+                .as_ref()
+                .map(|recurrence| recurrence.to_string()),
+        ),
+        ("Time", time_label(task)),
+        (
+            "Blocked by",
+            Some(todo_app.blocked_by(&index, task).join(", ")).filter(|labels| !labels.is_empty()),
+        ),
+        (
+            "Subtasks",
+            (!task.subtasks.is_empty()).then(|| format!("{} ({done} done)", task.subtasks.len())),
+        ),
+        ("Created", timestamp(task.created_at)),
+        ("Completed", timestamp(task.completed_at)),
+    ];
+
+    println!(
+        "\n=== TASK {} #{} ({}) ===",
+        path,
+        index.short_id(task),
+        todo_app.current()
+    );
+    println!("{}", task.description.bold());
+    for (name, value) in fields {
+        if let Some(value) = value {
+            println!("{:<11}{value}", name.bold());
+        }
+    }
+
+    if !task.note.is_empty() {
+        println!("\n{}", "Note".bold());
+        for line in task.note.lines() {
+            println!("  {line}");
+        }
+    }
+
+    if !task.attachments.is_empty() {
+        println!("\n{}", "Attachments".bold());
+        for (number, attachment) in task.attachments.iter().enumerate() {
+            let missing = !task::is_url(attachment) && !Path::new(attachment).exists();
+            if missing {
+                println!("  {}. {attachment} {}", number + 1, "(missing)".red());
+            } else {
+                println!("  {}. {attachment}", number + 1);
+            }
+        }
+    }
+
+    println!("==================\n");
+}
@@ -0,0 +1,50 @@
+use std::env;
+
+use todo_core::{
+    storage::{Storage, StorageError},
+    TodoError,
+};
+
+/// Passphrase of an encrypted data file, used instead of asking for it.
+const PASSPHRASE_ENV: &str = "TODO_PASSPHRASE";
+/// Passphrase for `todo encrypt` and `todo rekey`, used instead of asking for it.
+const NEW_PASSPHRASE_ENV: &str = "TODO_NEW_PASSPHRASE";
+
+/// Asks for the passphrase of an encrypted data file before it is loaded.
+pub fn unlock(store: &mut dyn Storage) -> Result<(), StorageError> {
+    if !store.is_encrypted()? {
+        return Ok(());
+    }
+
+    let passphrase = match env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) => passphrase,
+        Err(_) => {
+            rpassword::prompt_password(format!("Passphrase for {}: ", store.path().display()))
+                .map_err(|err| {
+                    StorageError::Encryption(format!(
+                        "Could not ask for the passphrase ({err}), set {PASSPHRASE_ENV} instead"
+                    ))
+                })?
+        }
+    };
+
+    store.unlock(&passphrase)
+}
+
+/// Asks for a new passphrase twice, so a typo does not lock the tasks away.
+pub fn read_new() -> Result<String, TodoError> {
+    if let Ok(passphrase) = env::var(NEW_PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+
+    let passphrase = rpassword::prompt_password("New passphrase: ").map_err(TodoError::Io)?;
+    let repeated = rpassword::prompt_password("Repeat passphrase: ").map_err(TodoError::Io)?;
+
+    if passphrase == repeated {
+        Ok(passphrase)
+    } else {
+        Err(TodoError::InvalidInput(
+            "The passphrases do not match".to_string(),
+        ))
+    }
+}
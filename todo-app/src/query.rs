@@ -0,0 +1,211 @@
+use chrono::{Days, NaiveDate, NaiveDateTime};
+use regex::{Regex, RegexBuilder};
+use std::str::FromStr;
+
+use crate::{
+    task::{Priority, Task},
+    tree::{self, TaskPath},
+};
+
+/// A single filter term. All terms of a query must match.
+enum Condition {
+    Completed(bool),
+    State(String),
+    Tag(String),
+    Priority(Priority),
+    Due(DueWindow),
+    DueBefore(NaiveDate),
+    DueAfter(NaiveDate),
+    Text(String),
+    Pattern(Regex),
+}
+
+enum DueWindow {
+    Overdue,
+    Today,
+    Week,
+    Any,
+    None,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SortKey {
+    Due,
+    Priority,
+    Created,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.to_lowercase().as_str() {
+            "due" => Ok(SortKey::Due),
+            "priority" => Ok(SortKey::Priority),
+            "created" => Ok(SortKey::Created),
+            _ => Err(format!(
+                "Invalid sort key: {input} (expected due, priority or created)"
+            )),
+        }
+    }
+}
+
+/// Filters and orders tasks for listing.
+///
+/// Filter terms are separated by spaces:
+/// `status:open|done`, `state:review`, `tag:ops` or `+ops`, `priority:high` or `!high`,
+/// `due:overdue|today|week|any|none`, `before:2026-11-01`, `after:2026-11-01`,
+/// `/regex/`, and any other word as a case-insensitive substring.
+#[derive(Default)]
+pub struct Query {
+    conditions: Vec<Condition>,
+    sort: Option<SortKey>,
+}
+
+impl Query {
+    pub fn parse(filter: &str, sort: Option<SortKey>) -> Result<Query, String> {
+        let conditions = filter
+            .split_whitespace()
+            .map(parse_condition)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Query { conditions, sort })
+    }
+
+    /// Reads the filter of a bulk selection. Bare status and state names
+    /// such as `done`, `open` or `review` pick by status there rather than
+    /// matching descriptions, so `done tag:ops` cannot pick open tasks that
+    /// mention "done".
+    pub fn parse_selection(filter: &str, states: &[String]) -> Result<Query, String> {
+        let conditions = filter
+            .split_whitespace()
+            .map(|term| match term.to_lowercase().as_str() {
+                "open" => Ok(Condition::Completed(false)),
+                "completed" => Ok(Condition::Completed(true)),
+                name if states.iter().any(|state| state == name) => {
+                    Ok(Condition::State(name.to_string()))
+                }
+                _ => parse_condition(term),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Query {
+            conditions,
+            sort: None,
+        })
+    }
+
+    pub fn matches(&self, task: &Task, now: NaiveDateTime) -> bool {
+        self.conditions
+            .iter()
+            .all(|condition| condition.matches(task, now))
+    }
+
+    /// Returns the matching tasks and subtasks with their path in the full tree.
+    pub fn apply<'a>(&self, tasks: &'a [Task], now: NaiveDateTime) -> Vec<(TaskPath, &'a Task)> {
+        let mut matches: Vec<(TaskPath, &Task)> = tree::flatten(tasks)
+            .into_iter()
+            .filter(|(_, task)| self.matches(task, now))
+            .collect();
+
+        // Tasks without the sort field go last; ties keep insertion order.
+        match self.sort {
+            Some(SortKey::Due) => {
+                matches.sort_by_key(|(_, task)| {
+                    (task.due.is_none(), task.due.map(|due| due.deadline()))
+                });
+            }
+            Some(SortKey::Priority) => {
+                matches.sort_by_key(|(_, task)| std::cmp::Reverse(task.priority));
+            }
+            Some(SortKey::Created) => {
+                matches.sort_by_key(|(_, task)| (task.created_at.is_none(), task.created_at));
+            }
+            None => {}
+        }
+
+        matches
+    }
+}
+
+impl Condition {
+    fn matches(&self, task: &Task, now: NaiveDateTime) -> bool {
+        let deadline = task.due.map(|due| due.deadline());
+
+        match self {
+            Condition::Completed(completed) => task.is_completed() == *completed,
+            Condition::State(state) => task.state == *state,
+            Condition::Tag(tag) => task.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+            Condition::Priority(priority) => task.priority == Some(*priority),
+            Condition::Due(DueWindow::Overdue) => task.is_overdue(now),
+            Condition::Due(DueWindow::Today) => task.due.is_some_and(|due| due.date == now.date()),
+            Condition::Due(DueWindow::Week) => deadline.is_some_and(|deadline| {
+                deadline >= now && deadline.date() < now.date() + Days::new(7)
+            }),
+            Condition::Due(DueWindow::Any) => task.due.is_some(),
+            Condition::Due(DueWindow::None) => task.due.is_none(),
+            Condition::DueBefore(date) => task.due.is_some_and(|due| due.date < *date),
+            Condition::DueAfter(date) => task.due.is_some_and(|due| due.date > *date),
+            Condition::Text(text) => task.description.to_lowercase().contains(text),
+            Condition::Pattern(pattern) => pattern.is_match(&task.description),
+        }
+    }
+}
+
+fn parse_condition(term: &str) -> Result<Condition, String> {
+    if term.len() > 1 && term.starts_with('/') && term.ends_with('/') {
+        let pattern = &term[1..term.len() - 1];
+
+        return RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map(Condition::Pattern)
+            .map_err(|err| format!("Invalid pattern {pattern}: {err}"));
+    }
+
+    if let Some(tag) = term.strip_prefix('+') {
+        return Ok(Condition::Tag(tag.to_string()));
+    }
+
+    if let Some(priority) = term.strip_prefix('!') {
+        return parse_priority(priority);
+    }
+
+    let Some((key, value)) = term.split_once(':') else {
+        return Ok(Condition::Text(term.to_lowercase()));
+    };
+
+    match key.to_lowercase().as_str() {
+        "status" => match value.to_lowercase().as_str() {
+            "open" | "todo" => Ok(Condition::Completed(false)),
+            "done" | "completed" => Ok(Condition::Completed(true)),
+            _ => Err(format!("Invalid status: {value} (expected open or done)")),
+        },
+        "state" => Ok(Condition::State(value.to_lowercase())),
+        "tag" => Ok(Condition::Tag(value.to_string())),
+        "priority" => parse_priority(value),
+        "due" => match value.to_lowercase().as_str() {
+            "overdue" => Ok(Condition::Due(DueWindow::Overdue)),
+            "today" => Ok(Condition::Due(DueWindow::Today)),
+            "week" => Ok(Condition::Due(DueWindow::Week)),
+            "any" => Ok(Condition::Due(DueWindow::Any)),
+            "none" => Ok(Condition::Due(DueWindow::None)),
+            _ => Err(format!(
+                "Invalid due window: {value} (expected overdue, today, week, any or none)"
+            )),
+        },
+        "before" => parse_date(value).map(Condition::DueBefore),
+        "after" => parse_date(value).map(Condition::DueAfter),
+        _ => Ok(Condition::Text(term.to_lowercase())),
+    }
+}
+
+fn parse_priority(value: &str) -> Result<Condition, String> {
+    Priority::parse(value)
+        .map(Condition::Priority)
+        .ok_or_else(|| format!("Invalid priority: {value}"))
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| format!("Invalid date: {value}"))
+}
@@ -0,0 +1,310 @@
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// Upper bound on the interval, so a typo cannot push due dates centuries ahead.
+const MAX_INTERVAL: u32 = 999;
+
+const WEEKDAYS: [(Weekday, &str, &str); 7] = [
+    (Weekday::Mon, "mon", "MO"),
+    (Weekday::Tue, "tue", "TU"),
+    (Weekday::Wed, "wed", "WE"),
+    (Weekday::Thu, "thu", "TH"),
+    (Weekday::Fri, "fri", "FR"),
+    (Weekday::Sat, "sat", "SA"),
+    (Weekday::Sun, "sun", "SU"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+/// How often a task repeats.
+///
+/// Written as `daily`, `weekly`, `monthly`, `mon,wed,fri`, `3d`, `2w`, `6m`,
+/// or an RRULE subset such as `FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    pub interval: u32,
+    /// Days of the week a weekly rule falls on; empty means the weekday of the due date.
+    pub weekdays: Vec<Weekday>,
+}
+
+impl Recurrence {
+    fn new(frequency: Frequency, interval: u32, weekdays: Vec<Weekday>) -> Result<Self, String> {
+        if interval == 0 || interval > MAX_INTERVAL {
+            return Err(format!("Invalid interval: {interval}"));
+        }
+
+        let mut weekdays = weekdays;
+        weekdays.sort_by_key(|day| day.num_days_from_monday());
+        weekdays.dedup();
+
+        Ok(Self {
+            frequency,
+            interval,
+            weekdays,
+        })
+    }
+
+    /// The first occurrence strictly after `date`.
+    pub fn next(&self, date: NaiveDate) -> NaiveDate {
+        match self.frequency {
+            Frequency::Daily => date + Days::new(self.interval as u64),
+            Frequency::Monthly => {
+                let next = date
+                    .checked_add_months(Months::new(self.interval))
+                    .unwrap_or(NaiveDate::MAX);
+
+                // Keep a rule that falls on the last day of the month there,
+                // instead of drifting from the 31st to the 30th to the 28th
+                if is_last_day_of_month(date) {
+                    last_day_of_month(next)
+                } else {
+                    next
+                }
+            }
+            Frequency::Weekly if self.weekdays.is_empty() => {
+                date + Days::new(7 * self.interval as u64)
+            }
+            Frequency::Weekly => {
+                let week_start =
+                    |day: NaiveDate| day - Days::new(day.weekday().num_days_from_monday() as u64);
+                let first_week = week_start(date);
+
+                // Only weeks that are a multiple of `interval` after the current one count
+                (1..=7 * (self.interval as u64 + 1))
+                    .map(|offset| date + Days::new(offset))
+                    .find(|day| {
+                        let weeks = (week_start(*day) - first_week).num_days() / 7;
+                        weeks % self.interval as i64 == 0 && self.weekdays.contains(&day.weekday())
+                    })
+                    .unwrap_or(date + Days::new(7 * self.interval as u64))
+            }
+        }
+    }
+
+    fn weekday_names(&self, rrule: bool) -> String {
+        let names: Vec<&str> = self
+            .weekdays
+            .iter()
+            .filter_map(|day| WEEKDAYS.iter().find(|(weekday, _, _)| weekday == day))
+            .map(|(_, short, code)| if rrule { *code } else { *short })
+            .collect();
+
+        names.join(",")
+    }
+
+    fn parse_rrule(input: &str) -> Result<Self, String> {
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut weekdays = Vec::new();
+
+        for part in input.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid rule part: {part}"))?;
+
+            match key.to_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        _ => return Err(format!("Unsupported frequency: {value}")),
+                    })
+                }
+                "INTERVAL" => {
+                    interval = value
+                        .parse()
+                        .map_err(|_| format!("Invalid interval: {value}"))?
+                }
+                "BYDAY" => weekdays = parse_weekdays(value)?,
+                _ => return Err(format!("Unsupported rule part: {key}")),
+            }
+        }
+
+        let frequency = frequency.ok_or("A rule needs a FREQ")?;
+
+        if !weekdays.is_empty() && frequency != Frequency::Weekly {
+            return Err("BYDAY is only supported with FREQ=WEEKLY".to_string());
+        }
+
+        Recurrence::new(frequency, interval, weekdays)
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let lower = input.to_lowercase();
+
+        if lower.starts_with("freq=") || lower.contains(";freq=") {
+            return Recurrence::parse_rrule(input);
+        }
+
+        match lower.as_str() {
+            "daily" => return Recurrence::new(Frequency::Daily, 1, Vec::new()),
+            "weekly" => return Recurrence::new(Frequency::Weekly, 1, Vec::new()),
+            "monthly" => return Recurrence::new(Frequency::Monthly, 1, Vec::new()),
+            _ => {}
+        }
+
+        for (suffix, frequency) in [
+            ('d', Frequency::Daily),
+            ('w', Frequency::Weekly),
+            ('m', Frequency::Monthly),
+        ] {
+            if let Some(Ok(interval)) = lower.strip_suffix(suffix).map(str::parse::<u32>) {
+                return Recurrence::new(frequency, interval, Vec::new());
+            }
+        }
+
+        parse_weekdays(&lower)
+            .map_err(|_| format!("Invalid recurrence: {input}"))
+            .and_then(|weekdays| Recurrence::new(Frequency::Weekly, 1, weekdays))
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.frequency, self.interval) {
+            (Frequency::Weekly, 1) if !self.weekdays.is_empty() => {
+                write!(f, "{}", self.weekday_names(false))
+            }
+            (Frequency::Weekly, interval) if !self.weekdays.is_empty() => write!(
+                f,
+                "FREQ=WEEKLY;INTERVAL={interval};BYDAY={}",
+                self.weekday_names(true)
+            ),
+            (Frequency::Daily, 1) => write!(f, "daily"),
+            (Frequency::Weekly, 1) => write!(f, "weekly"),
+            (Frequency::Monthly, 1) => write!(f, "monthly"),
+            (Frequency::Daily, interval) => write!(f, "{interval}d"),
+            (Frequency::Weekly, interval) => write!(f, "{interval}w"),
+            (Frequency::Monthly, interval) => write!(f, "{interval}m"),
+        }
+    }
+}
+
+impl TryFrom<String> for Recurrence {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Recurrence> for String {
+    fn from(recurrence: Recurrence) -> Self {
+        recurrence.to_string()
+    }
+}
+
+fn parse_weekdays(input: &str) -> Result<Vec<Weekday>, String> {
+    input
+        .split(',')
+        .map(|name| {
+            WEEKDAYS
+                .iter()
+                .find(|(_, short, rrule)| {
+                    name.eq_ignore_ascii_case(short) || name.eq_ignore_ascii_case(rrule)
+                })
+                .map(|(weekday, _, _)| *weekday)
+                .ok_or_else(|| format!("Invalid weekday: {name}"))
+        })
+        .collect()
+}
+
+fn is_last_day_of_month(date: NaiveDate) -> bool {
+    date.succ_opt()
+        .is_none_or(|next| next.month() != date.month())
+}
+
+pub fn last_day_of_month(date: NaiveDate) -> NaiveDate {
+    (date.day()..=31)
+        .rev()
+        .find_map(|day| date.with_day(day))
+        .unwrap_or(date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn rule(input: &str) -> Recurrence {
+        input.parse().unwrap()
+    }
+
+    #[test]
+    fn daily_and_weekly_intervals() {
+        assert_eq!(rule("3d").next(date(2026, 12, 30)), date(2027, 1, 2));
+        assert_eq!(rule("weekly").next(date(2026, 10, 14)), date(2026, 10, 21));
+        assert_eq!(rule("2w").next(date(2026, 10, 14)), date(2026, 10, 28));
+    }
+
+    #[test]
+    fn monthly_keeps_the_end_of_the_month() {
+        let monthly = rule("monthly");
+
+        assert_eq!(monthly.next(date(2026, 10, 15)), date(2026, 11, 15));
+        assert_eq!(monthly.next(date(2027, 1, 31)), date(2027, 2, 28));
+        assert_eq!(monthly.next(date(2027, 2, 28)), date(2027, 3, 31));
+        assert_eq!(monthly.next(date(2028, 1, 31)), date(2028, 2, 29));
+        assert_eq!(rule("6m").next(date(2026, 8, 31)), date(2027, 2, 28));
+    }
+
+    #[test]
+    fn weekdays_pick_the_next_one() {
+        let weekdays = rule("mon,wed,fri");
+
+        // Wednesday to Friday, then over the weekend to Monday
+        assert_eq!(weekdays.next(date(2026, 10, 14)), date(2026, 10, 16));
+        assert_eq!(weekdays.next(date(2026, 10, 16)), date(2026, 10, 19));
+    }
+
+    #[test]
+    fn weekdays_every_other_week() {
+        let rule = rule("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH");
+
+        // Monday to Thursday of the same week, then skip a week
+        assert_eq!(rule.next(date(2026, 10, 12)), date(2026, 10, 15));
+        assert_eq!(rule.next(date(2026, 10, 15)), date(2026, 10, 26));
+    }
+
+    #[test]
+    fn written_the_way_it_is_read() {
+        assert_eq!(rule("fri,MON,fri").to_string(), "mon,fri");
+        assert_eq!(rule("FREQ=DAILY;INTERVAL=1").to_string(), "daily");
+        assert_eq!(
+            rule("freq=weekly;byday=th,mo;interval=2").to_string(),
+            "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH"
+        );
+        assert_eq!(rule("12M").to_string(), "12m");
+    }
+
+    #[test]
+    fn invalid_rules() {
+        for input in [
+            "0d",
+            "1000w",
+            "yearly",
+            "FREQ=YEARLY",
+            "FREQ=DAILY;BYDAY=MO",
+            "INTERVAL=2",
+            "mon,someday",
+        ] {
+            assert!(input.parse::<Recurrence>().is_err(), "{input}");
+        }
+    }
+}
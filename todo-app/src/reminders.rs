@@ -0,0 +1,284 @@
+use chrono::{DateTime, Local, NaiveDateTime, TimeDelta};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+    thread,
+    time::Duration,
+};
+
+use todo_core::{dates::Timezone, lists::TaskList, task::Due, tree, TodoApp, TodoError};
+
+/// How long `todo snooze` and the `z` key hold back reminders by default.
+pub const DEFAULT_SNOOZE: &str = "10m";
+
+/// A task that is due soon, or overdue.
+pub struct Reminder {
+    pub id: String,
+    pub list: String,
+    pub index: String,
+    pub description: String,
+    pub due: Due,
+    pub is_overdue: bool,
+    pub snoozed_until: Option<DateTime<Local>>,
+}
+
+impl Reminder {
+    pub fn message(&self) -> String {
+        let when = if self.is_overdue {
+            format!("Overdue since {}", self.due)
+        } else {
+            format!("Due {}", self.due)
+        };
+
+        format!(
+            "{when}: {} ({} {})",
+            self.description, self.list, self.index
+        )
+    }
+}
+
+/// Somewhere to deliver reminders to.
+pub trait Notifier {
+    fn notify(&mut self, reminder: &Reminder) -> Result<(), String>;
+}
+
+/// Prints a line per reminder.
+pub struct StdoutNotifier;
+
+impl Notifier for StdoutNotifier {
+    fn notify(&mut self, reminder: &Reminder) -> Result<(), String> {
+        let time = Local::now().format("%H:%M");
+        let message = reminder.message();
+        let message = if reminder.is_overdue {
+            message.red().bold()
+        } else {
+            message.yellow()
+        };
+
+        println!("[{time}] {message}");
+        Ok(())
+    }
+}
+
+/// Rings the terminal bell.
+pub struct BellNotifier;
+
+impl Notifier for BellNotifier {
+    fn notify(&mut self, _reminder: &Reminder) -> Result<(), String> {
+        let mut stdout = io::stdout();
+        stdout
+            .write_all(b"\x07")
+            .and_then(|_| stdout.flush())
+            .map_err(|err| err.to_string())
+    }
+}
+
+/// Shows a desktop notification with `notify-send`, or `osascript` on macOS.
+pub struct DesktopNotifier;
+
+impl Notifier for DesktopNotifier {
+    fn notify(&mut self, reminder: &Reminder) -> Result<(), String> {
+        let message = reminder.message();
+        let mut command = if cfg!(target_os = "macos") {
+            let script = format!(
+                "display notification {:?} with title \"todo\"",
+                message.replace('"', "'")
+            );
+            let mut command = Command::new("osascript");
+            command.args(["-e", &script]);
+            command
+        } else {
+            let mut command = Command::new("notify-send");
+            command.args(["todo", &message]);
+            command
+        };
+
+        match command.status() {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(format!("Desktop notification failed: {status}")),
+            Err(err) => Err(format!(
+                "Could not run {}: {err}",
+                command.get_program().to_string_lossy()
+            )),
+        }
+    }
+}
+
+/// The notifiers that can be picked with `todo watch --notify`.
+#[derive(Debug, Clone, Copy)]
+pub enum NotifierKind {
+    Stdout,
+    Bell,
+    Desktop,
+}
+
+impl NotifierKind {
+    pub fn build(&self) -> Box<dyn Notifier> {
+        match self {
+            NotifierKind::Stdout => Box::new(StdoutNotifier),
+            NotifierKind::Bell => Box::new(BellNotifier),
+            NotifierKind::Desktop => Box::new(DesktopNotifier),
+        }
+    }
+}
+
+impl FromStr for NotifierKind {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.to_lowercase().as_str() {
+            "stdout" => Ok(NotifierKind::Stdout),
+            "bell" => Ok(NotifierKind::Bell),
+            "desktop" => Ok(NotifierKind::Desktop),
+            _ => Err(format!(
+                "Invalid notifier: {input} (expected stdout, bell or desktop)"
+            )),
+        }
+    }
+}
+
+/// Open tasks of every list that should be reminded of at `now`: `lead`
+/// before their due time, or from the start of the day for tasks without
+/// a time, both in `timezone`. Snoozed tasks are left out until the snooze ends.
+pub fn due_reminders(
+    lists: &[TaskList],
+    lead: TimeDelta,
+    now: DateTime<Local>,
+    timezone: Timezone,
+) -> Vec<Reminder> {
+    let naive_now = timezone.at(now);
+
+    lists
+        .iter()
+        .flat_map(|list| {
+            tree::flatten(&list.tasks)
+                .into_iter()
+                .filter(move |(_, task)| {
+                    !task.is_completed()
+                        && task.snoozed_until.is_none_or(|until| until <= now)
+                        && task
+                            .due
+                            .is_some_and(|due| remind_at(&due, lead) <= naive_now)
+                })
+                .filter_map(move |(path, task)| {
+                    Some(Reminder {
+                        id: task.id.clone(),
+                        list: list.name.clone(),
+                        index: path.to_string(),
+                        description: task.description.clone(),
+                        due: task.due?,
+                        is_overdue: task.is_overdue(naive_now),
+                        snoozed_until: task.snoozed_until,
+                    })
+                })
+        })
+        .collect()
+}
+
+fn remind_at(due: &Due, lead: TimeDelta) -> NaiveDateTime {
+    match due.time {
+        Some(time) => due.date.and_time(time) - lead,
+        None => due.date.and_hms_opt(0, 0, 0).unwrap_or(due.deadline()),
+    }
+}
+
+/// A reminder that was sent. Changing the due date or snoozing the task
+/// makes it a new one.
+#[derive(PartialEq, Eq, Hash, Serialize, Deserialize)]
+struct Sent {
+    id: String,
+    due: Due,
+    snoozed_until: Option<DateTime<Local>>,
+}
+
+impl Sent {
+    fn new(reminder: &Reminder) -> Self {
+        Self {
+            id: reminder.id.clone(),
+            due: reminder.due,
+            snoozed_until: reminder.snoozed_until,
+        }
+    }
+}
+
+/// Where the reminders sent are kept, next to the data file, e.g.
+/// `tasks.json.reminders.json`, so runs with `--once` do not send them again.
+/// The git store keeps the file out of the repository.
+fn sent_path(data_file: &Path) -> PathBuf {
+    let mut path = data_file.as_os_str().to_owned();
+    path.push(".reminders.json");
+    PathBuf::from(path)
+}
+
+/// The reminders sent by earlier runs; none if the file is missing or unreadable.
+fn load_sent(path: &Path) -> HashSet<Sent> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return HashSet::new();
+    };
+
+    serde_json::from_str(&content).unwrap_or_else(|err| {
+        eprintln!("{}", format!("Ignoring {}: {err}", path.display()).yellow());
+        HashSet::new()
+    })
+}
+
+fn save_sent(path: &Path, sent: &HashSet<Sent>) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(sent).map_err(|err| err.to_string())?;
+    fs::write(path, content).map_err(|err| {
+        format!(
+            "Could not save the reminders sent to {}: {err}",
+            path.display()
+        )
+    })
+}
+
+/// Checks the saved tasks every `interval` and sends each reminder once,
+/// also across runs. A task that is snoozed and comes due again is
+/// reminded of again.
+pub fn watch(
+    todo_app: &mut TodoApp,
+    notifiers: &mut [Box<dyn Notifier>],
+    lead: TimeDelta,
+    interval: Duration,
+    once: bool,
+) -> Result<(), TodoError> {
+    let path = sent_path(todo_app.path());
+    let mut sent = load_sent(&path);
+
+    loop {
+        // Pick up changes made by other todo processes since the last check
+        todo_app.reload()?;
+
+        let reminders = due_reminders(todo_app.lists(), lead, Local::now(), todo_app.timezone());
+        let due: HashSet<Sent> = reminders.iter().map(Sent::new).collect();
+        for reminder in &reminders {
+            if sent.contains(&Sent::new(reminder)) {
+                continue;
+            }
+            for notifier in notifiers.iter_mut() {
+                if let Err(err) = notifier.notify(reminder) {
+                    eprintln!("{}", err.red());
+                }
+            }
+        }
+
+        // Only what is still due is kept, so the file does not grow
+        if sent != due {
+            if let Err(err) = save_sent(&path, &due) {
+                eprintln!("{}", err.red());
+            }
+            sent = due;
+        }
+
+        if once {
+            return Ok(());
+        }
+        thread::sleep(interval);
+    }
+}
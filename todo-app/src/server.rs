@@ -0,0 +1,589 @@
+use chrono::{DateTime, Local};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    io::{self, Read},
+    net::Ipv4Addr,
+};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use todo_core::{
+    dependencies::TaskIndex,
+    query::{Query, SortKey},
+    recurrence::Recurrence,
+    storage::StorageError,
+    task::{Due, Priority, Task},
+    timelog::Effort,
+    tree::{TaskPath, TaskRef},
+    TodoApp, TodoError,
+};
+
+/// Port `todo serve` listens on unless `--port` says otherwise.
+pub const DEFAULT_PORT: u16 = 7878;
+/// Request bodies above this size are rejected.
+const MAX_BODY: u64 = 1024 * 1024;
+/// Names the server is reached by, followed by `:` and the port in `Host`.
+const LOCAL_HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "[::1]"];
+
+/// A task as the API returns it. Subtasks are listed by id, and come as
+/// tasks of their own when listing.
+#[derive(Serialize)]
+struct TaskView<'a> {
+    id: &'a str,
+    list: &'a str,
+    /// Where the task is in its list, e.g. `2.1`; changes as tasks are added and removed.
+    index: String,
+    description: &'a str,
+    state: &'a str,
+    due: Option<Due>,
+    priority: Option<Priority>,
+    tags: &'a [String],
+    recurrence: Option<&'a Recurrence>,
+    estimate: Option<Effort>,
+    depends_on: &'a [String],
+    /// Ids of the open tasks this one waits for.
+    blocked_by: Vec<&'a str>,
+    created_at: Option<DateTime<Local>>,
+    completed_at: Option<DateTime<Local>>,
+    note: &'a str,
+    attachments: &'a [String],
+    subtasks: Vec<&'a str>,
+}
+
+impl<'a> TaskView<'a> {
+    fn new(index: &TaskIndex<'a>, list: &'a str, path: &TaskPath, task: &'a Task) -> Self {
+        Self {
+            id: &task.id,
+            list,
+            index: path.to_string(),
+            description: &task.description,
+            state: &task.state,
+            due: task.due,
+            priority: task.priority,
+            tags: &task.tags,
+            recurrence: task.recurrence.as_ref(),
+            estimate: task.estimate,
+            depends_on: &task.depends_on,
+            blocked_by: index
+                .blockers(task)
+                .into_iter()
+                .map(|entry| entry.task.id.as_str())
+                .collect(),
+            created_at: task.created_at,
+            completed_at: task.completed_at,
+            note: &task.note,
+            attachments: &task.attachments,
+            subtasks: task.subtasks.iter().map(|task| task.id.as_str()).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Listing<'a> {
+    list: &'a str,
+    /// Also sent as the `ETag` of the response.
+    version: u64,
+    tasks: Vec<TaskView<'a>>,
+}
+
+#[derive(Serialize)]
+struct ListView<'a> {
+    name: &'a str,
+    /// Top-level tasks.
+    tasks: usize,
+    /// Whether this is the current list, as picked with `todo lists switch`.
+    current: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NewTask {
+    /// Inline syntax, as with `todo add`.
+    description: String,
+    /// Id of the task to add this one under.
+    parent: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Changes {
+    /// Inline syntax, as with `todo edit`.
+    description: Option<String>,
+    /// Markdown; an empty one removes the note.
+    note: Option<String>,
+    state: Option<String>,
+    /// Move a blocked task to `done` anyway.
+    #[serde(default)]
+    force: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Completion {
+    #[serde(default)]
+    force: bool,
+}
+
+/// What a request is answered with: a status and a JSON body, if any.
+struct Reply {
+    status: u16,
+    body: Option<String>,
+}
+
+impl Reply {
+    fn json(status: u16, body: impl Serialize) -> Self {
+        match serde_json::to_string_pretty(&body) {
+            Ok(body) => Self {
+                status,
+                body: Some(body),
+            },
+            Err(err) => Reply::error(500, &err.to_string()),
+        }
+    }
+
+    fn empty(status: u16) -> Self {
+        Self { status, body: None }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, json!({ "error": message }))
+    }
+}
+
+impl From<TodoError> for Reply {
+    fn from(err: TodoError) -> Self {
+        let status = match err {
+            TodoError::InvalidIndex(_) | TodoError::UnknownId(_) | TodoError::UnknownList(_) => 404,
+            TodoError::InvalidInput(_) => 400,
+            TodoError::Blocked(_) | TodoError::Storage(StorageError::Conflict) => 409,
+            TodoError::History(_)
+            | TodoError::Io(_)
+            | TodoError::Storage(_)
+            | TodoError::Sync(_) => 500,
+        };
+
+        Reply::error(status, &err.to_string())
+    }
+}
+
+/// Answers requests on localhost until the process is stopped.
+///
+/// Every request first loads the tasks again, so changes made with other
+/// `todo` commands show up right away. Responses carry the revision of the
+/// data file as their `ETag`; requests that change a task have to send it
+/// back in `If-Match`, and get `412 Precondition Failed` if the tasks were
+/// saved since, instead of overwriting that change.
+pub fn serve(
+    todo_app: &mut TodoApp,
+    port: u16,
+    allow_origin: Option<&str>,
+) -> Result<(), TodoError> {
+    let allow_origin = allow_origin
+        .map(|origin| {
+            header("Access-Control-Allow-Origin", origin)
+                .ok_or_else(|| TodoError::InvalidInput(format!("Invalid origin: {origin}")))
+        })
+        .transpose()?;
+
+    // A list picked with `--list` is served instead of the saved current one
+    let list =
+        (todo_app.current() != todo_app.default_list()).then(|| todo_app.current().to_string());
+
+    let server = Server::http((Ipv4Addr::LOCALHOST, port))
+        .map_err(|err| TodoError::Io(io::Error::other(err)))?;
+    println!("Serving tasks on http://{}:{port}", Ipv4Addr::LOCALHOST);
+
+    for mut request in server.incoming_requests() {
+        let reply = if !is_local_host(&request, port) {
+            // A page that points its own domain at 127.0.0.1 would otherwise
+            // reach the API as if it were served from there
+            Reply::error(403, "Send requests to localhost or 127.0.0.1")
+        } else if *request.method() == Method::Options && allow_origin.is_some() {
+            Reply::empty(204)
+        } else {
+            handle(todo_app, list.as_deref(), &mut request).unwrap_or_else(|reply| reply)
+        };
+
+        println!("{} {} {}", request.method(), request.url(), reply.status);
+        respond(request, reply, todo_app.version(), allow_origin.as_ref());
+    }
+
+    Ok(())
+}
+
+fn handle(
+    todo_app: &mut TodoApp,
+    list: Option<&str>,
+    request: &mut Request,
+) -> Result<Reply, Reply> {
+    todo_app.reload().map_err(TodoError::Storage)?;
+
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let params: HashMap<String, String> = form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect();
+
+    let list = match params.get("list") {
+        Some(name) => name.clone(),
+        None => list.unwrap_or(todo_app.default_list()).to_string(),
+    };
+    todo_app.select(&list)?;
+
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    // tiny_http leaves out the body when answering HEAD
+    let method = match request.method() {
+        Method::Head => Method::Get,
+        method => method.clone(),
+    };
+
+    match (&method, segments.as_slice()) {
+        (Method::Get, ["lists"]) => Ok(lists(todo_app)),
+        (Method::Get, ["tasks"]) => list_tasks(todo_app, &params),
+        (Method::Post, ["tasks"]) => {
+            let new_task: NewTask = read_json(request)?;
+            create(todo_app, new_task)
+        }
+        (Method::Get, ["tasks", id]) => {
+            let path = todo_app.resolve(&task_ref(id)?)?;
+            Ok(view(todo_app, &path, 200)?)
+        }
+        (Method::Patch, ["tasks", id]) => {
+            check_version(todo_app, request)?;
+            let changes: Changes = read_json(request)?;
+            update(todo_app, id, changes)
+        }
+        (Method::Post, ["tasks", id, "complete"]) => {
+            check_version(todo_app, request)?;
+            let completion: Completion = read_json(request)?;
+            let path = todo_app.resolve(&task_ref(id)?)?;
+            todo_app.complete(&path, completion.force)?;
+            Ok(view(todo_app, &path, 200)?)
+        }
+        (Method::Delete, ["tasks", id]) => {
+            check_version(todo_app, request)?;
+            let path = todo_app.resolve(&task_ref(id)?)?;
+            todo_app.remove(&path)?;
+            Ok(Reply::empty(204))
+        }
+        (_, ["lists"] | ["tasks"] | ["tasks", _] | ["tasks", _, "complete"]) => Err(Reply::error(
+            405,
+            &format!("{method} is not allowed on {path}"),
+        )),
+        _ => Err(Reply::error(404, &format!("No such endpoint: {path}"))),
+    }
+}
+
+fn lists(todo_app: &TodoApp) -> Reply {
+    let lists: Vec<ListView> = todo_app
+        .lists()
+        .iter()
+        .map(|list| ListView {
+            name: &list.name,
+            tasks: list.tasks.len(),
+            current: list.name == todo_app.default_list(),
+        })
+        .collect();
+
+    Reply::json(200, lists)
+}
+
+/// Tasks and subtasks of the list matching `filter`, in the query syntax of `todo list`.
+fn list_tasks(todo_app: &TodoApp, params: &HashMap<String, String>) -> Result<Reply, Reply> {
+    let sort = params
+        .get("sort")
+        .map(|sort| sort.parse::<SortKey>())
+        .transpose()
+        .map_err(TodoError::InvalidInput)?;
+    let query = Query::parse(
+        params.get("filter").map(String::as_str).unwrap_or_default(),
+        sort,
+    )
+    .map_err(TodoError::InvalidInput)?;
+
+    let index = TaskIndex::new(todo_app.lists());
+    let tasks: Vec<TaskView> = query
+        .apply(todo_app.items(), todo_app.now())
+        .into_iter()
+        .map(|(path, task)| TaskView::new(&index, todo_app.current(), &path, task))
+        .collect();
+
+    Ok(Reply::json(
+        200,
+        Listing {
+            list: todo_app.current(),
+            version: todo_app.version(),
+            tasks,
+        },
+    ))
+}
+
+fn create(todo_app: &mut TodoApp, new_task: NewTask) -> Result<Reply, Reply> {
+    let parent = new_task
+        .parent
+        .map(|parent| task_ref(&parent).and_then(|parent| todo_app.resolve(&parent)))
+        .transpose()?;
+    let path = todo_app.add(&new_task.description, parent.as_ref())?;
+
+    Ok(view(todo_app, &path, 201)?)
+}
+
+/// Applies the description, the note and the state as a single change.
+fn update(todo_app: &mut TodoApp, id: &str, changes: Changes) -> Result<Reply, Reply> {
+    let path = todo_app.resolve(&task_ref(id)?)?;
+
+    todo_app.update(
+        &path,
+        changes.description.as_deref(),
+        changes.note.as_deref(),
+        changes.state.as_deref(),
+        changes.force,
+    )?;
+
+    Ok(view(todo_app, &path, 200)?)
+}
+
+/// Answers with the task at `path` in the current list.
+fn view(todo_app: &TodoApp, path: &TaskPath, status: u16) -> Result<Reply, TodoError> {
+    let index = TaskIndex::new(todo_app.lists());
+    let task = todo_app.task(path)?;
+
+    Ok(Reply::json(
+        status,
+        TaskView::new(&index, todo_app.current(), path, task),
+    ))
+}
+
+/// Tasks are addressed by id in URLs and bodies, or by a unique start of one.
+fn task_ref(id: &str) -> Result<TaskRef, TodoError> {
+    format!("#{}", id.trim_start_matches('#'))
+        .parse()
+        .map_err(TodoError::InvalidInput)
+}
+
+/// Makes sure the client saw the latest version of the tasks before it changes one.
+fn check_version(todo_app: &TodoApp, request: &Request) -> Result<(), Reply> {
+    let version = todo_app.version();
+    let Some(expected) = header_value(request, "If-Match") else {
+        return Err(Reply::json(
+            428,
+            json!({
+                "error": "Send the version the change is based on in If-Match",
+                "version": version,
+            }),
+        ));
+    };
+
+    let matches = expected.split(',').map(str::trim).any(|tag| {
+        tag == "*" || tag.trim_start_matches("W/").trim_matches('"') == version.to_string()
+    });
+    if matches {
+        Ok(())
+    } else {
+        Err(Reply::json(
+            412,
+            json!({
+                "error": "The tasks changed since they were read, fetch them again",
+                "version": version,
+            }),
+        ))
+    }
+}
+
+/// Whether the request was addressed to this machine, as `Host: localhost:7878`.
+fn is_local_host(request: &Request, port: u16) -> bool {
+    header_value(request, "Host").is_some_and(|host| {
+        LOCAL_HOSTS.iter().any(|name| {
+            host.eq_ignore_ascii_case(&format!("{name}:{port}"))
+                || (port == 80 && host.eq_ignore_ascii_case(name))
+        })
+    })
+}
+
+/// Reads a JSON body; an empty body counts as `{}`.
+fn read_json<T: DeserializeOwned>(request: &mut Request) -> Result<T, Reply> {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .take(MAX_BODY + 1)
+        .read_to_string(&mut body)
+        .map_err(|err| Reply::error(400, &format!("Could not read the request: {err}")))?;
+
+    if body.len() as u64 > MAX_BODY {
+        return Err(Reply::error(413, "Request body is too large"));
+    }
+    if body.trim().is_empty() {
+        body = "{}".to_string();
+    } else if !header_value(request, "Content-Type")
+        .is_some_and(|content_type| content_type.starts_with("application/json"))
+    {
+        // Browsers only send JSON to another origin after asking, which
+        // keeps other web pages from changing tasks without `--allow-origin`
+        return Err(Reply::error(415, "Send the body as application/json"));
+    }
+
+    serde_json::from_str(&body).map_err(|err| Reply::error(400, &format!("Invalid body: {err}")))
+}
+
+fn header_value<'r>(request: &'r Request, name: &'static str) -> Option<&'r str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+fn header(name: &str, value: &str) -> Option<Header> {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).ok()
+}
+
+fn respond(request: Request, reply: Reply, version: u64, allow_origin: Option<&Header>) {
+    let mut headers = vec![header("ETag", &format!("\"{version}\""))];
+    if reply.body.is_some() {
+        headers.push(header("Content-Type", "application/json"));
+    }
+    if let Some(origin) = allow_origin {
+        headers.extend([
+            Some(origin.clone()),
+            header("Access-Control-Allow-Methods", "GET, POST, PATCH, DELETE"),
+            header("Access-Control-Allow-Headers", "Content-Type, If-Match"),
+            header("Access-Control-Expose-Headers", "ETag"),
+        ]);
+    }
+
+    let response = headers.into_iter().flatten().fold(
+        Response::from_string(reply.body.unwrap_or_default()).with_status_code(reply.status),
+        |response, header| response.with_header(header),
+    );
+
+    if let Err(err) = request.respond(response) {
+        eprintln!("Failed to answer request: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+    use tiny_http::TestRequest;
+
+    use super::*;
+    use todo_core::{dates::Timezone, storage::JsonStore};
+
+    /// A data file of its own, removed with its directory at the end of the test.
+    struct DataDir(PathBuf);
+
+    impl DataDir {
+        fn new() -> Self {
+            let path = env::temp_dir().join(format!("todo-serve-{}", uuid::Uuid::new_v4()));
+            fs::create_dir(&path).unwrap();
+            Self(path)
+        }
+
+        fn open(&self) -> TodoApp {
+            let store = JsonStore::new(self.0.join("tasks.json"));
+            TodoApp::new(Box::new(store), Timezone::Local).unwrap()
+        }
+    }
+
+    impl Drop for DataDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn request(
+        method: Method,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: &'static str,
+    ) -> Request {
+        headers
+            .iter()
+            .fold(
+                TestRequest::new()
+                    .with_method(method)
+                    .with_path(path)
+                    .with_body(body),
+                |request, (name, value)| {
+                    request.with_header(Header::from_bytes(*name, *value).unwrap())
+                },
+            )
+            .into()
+    }
+
+    /// Renames the first task, sending `If-Match` if given.
+    fn rename(todo_app: &mut TodoApp, if_match: Option<&str>) -> u16 {
+        let mut headers = vec![("Content-Type", "application/json")];
+        headers.extend(if_match.map(|version| ("If-Match", version)));
+        let path = format!("/tasks/{}", todo_app.items()[0].id);
+        let mut request = request(
+            Method::Patch,
+            &path,
+            &headers,
+            r#"{"description": "Ship it"}"#,
+        );
+
+        handle(todo_app, None, &mut request)
+            .unwrap_or_else(|reply| reply)
+            .status
+    }
+
+    #[test]
+    fn changes_need_the_current_version() {
+        let dir = DataDir::new();
+        let mut todo_app = dir.open();
+        todo_app.add("Ship", None).unwrap();
+        let version = todo_app.version().to_string();
+
+        assert_eq!(rename(&mut todo_app, None), 428);
+        assert_eq!(rename(&mut todo_app, Some("\"0\"")), 412);
+        assert_eq!(rename(&mut todo_app, Some("\"7\", \"8\"")), 412);
+        assert_eq!(todo_app.items()[0].description, "Ship");
+
+        assert_eq!(
+            rename(&mut todo_app, Some(&format!("W/\"{version}\""))),
+            200
+        );
+        assert_eq!(todo_app.items()[0].description, "Ship it");
+        // The change saved a new version, so the old one no longer matches
+        assert_eq!(rename(&mut todo_app, Some(&format!("\"{version}\""))), 412);
+        assert_eq!(rename(&mut todo_app, Some("*")), 200);
+    }
+
+    #[test]
+    fn bodies_have_to_be_json() {
+        let dir = DataDir::new();
+        let mut todo_app = dir.open();
+        let mut request = request(Method::Post, "/tasks", &[], r#"{"description": "Ship"}"#);
+
+        let reply = handle(&mut todo_app, None, &mut request).unwrap_or_else(|reply| reply);
+
+        assert_eq!(reply.status, 415);
+        assert!(todo_app.items().is_empty());
+    }
+
+    #[test]
+    fn only_local_hosts_are_served() {
+        let host = |value: &str, port| {
+            is_local_host(
+                &request(Method::Get, "/tasks", &[("Host", value)], ""),
+                port,
+            )
+        };
+
+        assert!(host("localhost:7878", 7878));
+        assert!(host("LOCALHOST:7878", 7878));
+        assert!(host("127.0.0.1:7878", 7878));
+        assert!(host("[::1]:7878", 7878));
+        assert!(host("localhost", 80));
+        assert!(!host("localhost", 7878));
+        assert!(!host("localhost:8080", 7878));
+        assert!(!host("attacker.example:7878", 7878));
+        assert!(!host("localhost.attacker.example:7878", 7878));
+        assert!(!is_local_host(
+            &request(Method::Get, "/tasks", &[], ""),
+            7878
+        ));
+    }
+}
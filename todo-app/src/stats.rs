@@ -0,0 +1,184 @@
+use chrono::{Days, NaiveDate, NaiveDateTime, TimeDelta};
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+
+use crate::{dates::Timezone, task::Task, timelog};
+
+/// Width of the longest bar in a chart, in characters.
+const BAR_WIDTH: usize = 40;
+const BAR: char = '#';
+
+/// Completion counts, time to complete and overdue tasks over a range of
+/// days, read from the timestamps the tasks keep.
+#[derive(Serialize)]
+pub struct Stats {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Tasks created in the range.
+    pub created: usize,
+    /// Tasks completed in the range.
+    pub completed: usize,
+    /// Tasks completed in the range after they were due.
+    pub completed_late: usize,
+    /// Open tasks past their due date now.
+    pub overdue: usize,
+    /// Open tasks now.
+    pub open: usize,
+    /// Mean time from creation to completion of the tasks completed in the range.
+    #[serde(rename = "average_minutes_to_complete", serialize_with = "minutes")]
+    pub average_to_complete: Option<TimeDelta>,
+    /// Completions on every day of the range, including days without any.
+    pub per_day: BTreeMap<NaiveDate, usize>,
+    /// Completions per ISO week, e.g. `2026-W42`.
+    pub per_week: BTreeMap<String, usize>,
+    pub tags: BTreeMap<String, TagStats>,
+}
+
+#[derive(Default, Serialize)]
+pub struct TagStats {
+    pub completed: usize,
+    pub open: usize,
+    pub overdue: usize,
+}
+
+impl Stats {
+    /// Counts over `tasks` between the start of `from` and the end of `to`,
+    /// with dates taken in `timezone`.
+    pub fn new<'a>(
+        tasks: impl IntoIterator<Item = &'a Task>,
+        from: NaiveDate,
+        to: NaiveDate,
+        now: NaiveDateTime,
+        timezone: Timezone,
+    ) -> Self {
+        let per_day: BTreeMap<NaiveDate, usize> = from
+            .iter_days()
+            .take_while(|day| *day <= to)
+            .map(|day| (day, 0))
+            .collect();
+        let per_week = per_day.keys().map(|day| (week(*day), 0)).collect();
+
+        let mut stats = Stats {
+            from,
+            to,
+            created: 0,
+            completed: 0,
+            completed_late: 0,
+            overdue: 0,
+            open: 0,
+            average_to_complete: None,
+            per_day,
+            per_week,
+            tags: BTreeMap::new(),
+        };
+        let in_range = |day: NaiveDate| from <= day && day <= to;
+        let mut to_complete = Vec::new();
+
+        for task in tasks {
+            let created = task.created_at.map(|created| timezone.at(created));
+            let completed = task
+                .completed_at
+                .map(|completed| timezone.at(completed))
+                .filter(|completed| task.is_completed() && in_range(completed.date()));
+            let overdue = task.is_overdue(now);
+
+            if created.is_some_and(|created| in_range(created.date())) {
+                stats.created += 1;
+            }
+            if !task.is_completed() {
+                stats.open += 1;
+            }
+            if overdue {
+                stats.overdue += 1;
+            }
+
+            if let Some(completed) = completed {
+                stats.completed += 1;
+                *stats.per_day.entry(completed.date()).or_default() += 1;
+                *stats.per_week.entry(week(completed.date())).or_default() += 1;
+
+                if task.due.is_some_and(|due| completed > due.deadline()) {
+                    stats.completed_late += 1;
+                }
+                if let Some(created) = created {
+                    to_complete.push((completed - created).max(TimeDelta::zero()));
+                }
+            }
+
+            let tags: Vec<String> = if task.tags.is_empty() {
+                vec!["(untagged)".to_string()]
+            } else {
+                task.tags.iter().map(|tag| format!("+{tag}")).collect()
+            };
+            for tag in tags {
+                let counts = stats.tags.entry(tag).or_default();
+                counts.completed += completed.is_some() as usize;
+                counts.open += !task.is_completed() as usize;
+                counts.overdue += overdue as usize;
+            }
+        }
+
+        stats
+            .tags
+            .retain(|_, counts| counts.completed + counts.open > 0);
+        stats.average_to_complete = (!to_complete.is_empty())
+            .then(|| to_complete.iter().sum::<TimeDelta>() / to_complete.len() as i32);
+
+        stats
+    }
+
+    /// The range a stats view covers by default: the four weeks up to `today`.
+    pub fn default_from(to: NaiveDate) -> NaiveDate {
+        to - Days::new(27)
+    }
+}
+
+/// Draws one bar per row, scaled so the largest value fills `BAR_WIDTH`,
+/// followed by the value.
+pub fn bar_chart<'a>(rows: impl IntoIterator<Item = (String, &'a usize)>) -> String {
+    let rows: Vec<(String, usize)> = rows
+        .into_iter()
+        .map(|(label, value)| (label, *value))
+        .collect();
+    let label_width = rows
+        .iter()
+        .map(|(label, _)| label.chars().count())
+        .max()
+        .unwrap_or(0);
+    let max = rows
+        .iter()
+        .map(|(_, value)| *value)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    rows.iter()
+        .map(|(label, value)| {
+            // Round up, so every non-zero value shows at least one mark
+            let length = (value * BAR_WIDTH).div_ceil(max);
+            let bar: String = std::iter::repeat_n(BAR, length).collect();
+            format!(
+                "{label:>label_width$} | {bar}{}{value}",
+                if length > 0 { " " } else { "" }
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Writes a duration as `3d 4h`, or `4h20m` below a day.
+pub fn format_age(delta: TimeDelta) -> String {
+    match (delta.num_days(), delta.num_hours() % 24) {
+        (0, _) => timelog::format_delta(delta),
+        (days, 0) => format!("{days}d"),
+        (days, hours) => format!("{days}d {hours}h"),
+    }
+}
+
+fn week(day: NaiveDate) -> String {
+    day.format("%G-W%V").to_string()
+}
+
+fn minutes<S: Serializer>(delta: &Option<TimeDelta>, serializer: S) -> Result<S::Ok, S::Error> {
+    delta.map(|delta| delta.num_minutes()).serialize(serializer)
+}
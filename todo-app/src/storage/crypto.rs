@@ -0,0 +1,160 @@
+use aes_gcm::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+
+use super::StorageError;
+
+const KDF: &str = "argon2id";
+const CIPHER: &str = "aes-256-gcm";
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+/// AES-GCM takes 96-bit nonces, random for every save.
+const NONCE_LEN: usize = 12;
+
+/// How an encrypted data file was sealed, next to the sealed data itself.
+/// The KDF costs are stored so they can be raised later without breaking
+/// older files.
+#[derive(Serialize, Deserialize)]
+pub struct Sealed {
+    kdf: String,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    salt: String,
+    cipher: String,
+    nonce: String,
+    data: String,
+}
+
+/// A key derived from a passphrase, kept with the salt it was derived with
+/// so that saves do not have to run the KDF again.
+pub struct Cipher {
+    passphrase: String,
+    salt: Vec<u8>,
+    params: Params,
+    key: Key<Aes256Gcm>,
+}
+
+impl Cipher {
+    /// Derives a key from `passphrase` with a new random salt.
+    pub fn new(passphrase: &str) -> Result<Self, StorageError> {
+        let mut salt = vec![0; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+
+        Self::derive(passphrase, salt, Params::default())
+    }
+
+    fn derive(passphrase: &str, salt: Vec<u8>, params: Params) -> Result<Self, StorageError> {
+        let mut key = Key::<Aes256Gcm>::default();
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params.clone())
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|err| StorageError::Encryption(format!("Could not derive key: {err}")))?;
+
+        Ok(Self {
+            passphrase: passphrase.to_string(),
+            salt,
+            params,
+            key,
+        })
+    }
+
+    pub fn passphrase(&self) -> &str {
+        &self.passphrase
+    }
+
+    /// Derives the key `sealed` was encrypted with from `passphrase`, or
+    /// reuses this one if it was derived the same way.
+    pub fn for_sealed(
+        cached: Option<Cipher>,
+        passphrase: &str,
+        sealed: &Sealed,
+    ) -> Result<Self, StorageError> {
+        if sealed.kdf != KDF || sealed.cipher != CIPHER {
+            return Err(StorageError::Encryption(format!(
+                "Unsupported encryption {} with {}",
+                sealed.cipher, sealed.kdf
+            )));
+        }
+
+        let salt = decode(&sealed.salt)?;
+        let params = Params::new(
+            sealed.memory_kib,
+            sealed.iterations,
+            sealed.parallelism,
+            Some(KEY_LEN),
+        )
+        .map_err(|err| StorageError::Encryption(format!("Invalid key derivation: {err}")))?;
+
+        match cached {
+            Some(cipher)
+                if cipher.passphrase == passphrase
+                    && cipher.salt == salt
+                    && cipher.params == params =>
+            {
+                Ok(cipher)
+            }
+            _ => Self::derive(passphrase, salt, params),
+        }
+    }
+
+    /// Encrypts `plaintext`, binding it to `revision` so the revision kept
+    /// in the clear cannot be changed without notice.
+    pub fn seal(&self, plaintext: &[u8], revision: u64) -> Result<Sealed, StorageError> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let aad = associated_data(revision);
+        let data = Aes256Gcm::new(&self.key)
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| StorageError::Encryption("Could not encrypt the tasks".to_string()))?;
+
+        Ok(Sealed {
+            kdf: KDF.to_string(),
+            memory_kib: self.params.m_cost(),
+            iterations: self.params.t_cost(),
+            parallelism: self.params.p_cost(),
+            salt: STANDARD.encode(&self.salt),
+            cipher: CIPHER.to_string(),
+            nonce: STANDARD.encode(nonce),
+            data: STANDARD.encode(data),
+        })
+    }
+
+    /// Decrypts `sealed`. A wrong passphrase and a file changed outside of
+    /// todo look the same to the cipher, so both fail with `WrongPassphrase`.
+    pub fn open(&self, sealed: &Sealed, revision: u64) -> Result<Vec<u8>, StorageError> {
+        let nonce = decode(&sealed.nonce)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(StorageError::Encryption("Invalid nonce".to_string()));
+        }
+        let aad = associated_data(revision);
+
+        Aes256Gcm::new(&self.key)
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &decode(&sealed.data)?,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| StorageError::WrongPassphrase)
+    }
+}
+
+fn associated_data(revision: u64) -> Vec<u8> {
+    format!("todo-app revision {revision}").into_bytes()
+}
+
+fn decode(input: &str) -> Result<Vec<u8>, StorageError> {
+    STANDARD
+        .decode(input)
+        .map_err(|err| StorageError::Encryption(format!("Invalid encrypted data: {err}")))
+}
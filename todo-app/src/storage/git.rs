@@ -0,0 +1,314 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use super::{create_parent_dir, json, Data, JsonStore, Storage, StorageError, SyncReport};
+use crate::{history::History, lists::TaskList, merge, workflow::Workflow};
+
+/// Files written next to the data file, kept out of git: the JSON store's
+/// lock and temp files, and the reminders already sent.
+const IGNORED_SUFFIXES: &[&str] = &[".lock", ".tmp", ".reminders.json"];
+
+/// Keeps the tasks in a JSON file inside a git working tree and commits
+/// every change, so the file can be shared through a remote with `todo sync`.
+pub struct GitStore {
+    json: JsonStore,
+    /// The directory holding the data file, inside the working tree.
+    dir: PathBuf,
+    /// The data file relative to `dir`, as git expects it after a revision.
+    file: String,
+}
+
+impl GitStore {
+    /// Opens the data file, starting a repository in its directory if it is
+    /// not in a working tree yet.
+    pub fn open(path: PathBuf) -> Result<Self, StorageError> {
+        create_parent_dir(&path)?;
+
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let file = path
+            .file_name()
+            .map(|name| format!("./{}", name.to_string_lossy()))
+            .ok_or_else(|| StorageError::Git(format!("Invalid data file {}", path.display())))?;
+
+        let store = Self {
+            json: JsonStore::new(path),
+            dir,
+            file,
+        };
+
+        let in_work_tree = store
+            .run(&["rev-parse", "--is-inside-work-tree"])?
+            .status
+            .success();
+        if !in_work_tree {
+            store.git(&["init", "--quiet"])?;
+        }
+        store.ignore_scratch_files()?;
+
+        Ok(store)
+    }
+
+    fn run(&self, args: &[&str]) -> Result<Output, StorageError> {
+        Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            .output()
+            .map_err(|err| StorageError::Git(format!("Could not run git: {err}")))
+    }
+
+    /// Runs git and returns its output, or its error message if it failed.
+    fn git(&self, args: &[&str]) -> Result<String, StorageError> {
+        let output = self.run(args)?;
+
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(StorageError::Git(format!(
+                "git {} failed: {}",
+                args.first().unwrap_or(&""),
+                stderr.trim()
+            )))
+        }
+    }
+
+    /// The commit checked out, or none on a branch without commits yet.
+    fn head(&self) -> Result<Option<String>, StorageError> {
+        if self.check(&["rev-parse", "--verify", "--quiet", "HEAD"])? {
+            Ok(Some(self.git(&["rev-parse", "HEAD"])?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Whether a git command that answers with its exit status said yes.
+    fn check(&self, args: &[&str]) -> Result<bool, StorageError> {
+        Ok(self.run(args)?.status.success())
+    }
+
+    /// Lists the files next to the data file in `.git/info/exclude`, which applies
+    /// to this clone only and needs no commit.
+    fn ignore_scratch_files(&self) -> Result<(), StorageError> {
+        let exclude = self
+            .dir
+            .join(self.git(&["rev-parse", "--git-path", "info/exclude"])?);
+        let existing = fs::read_to_string(&exclude).unwrap_or_default();
+        let name = self.file.trim_start_matches("./");
+
+        let missing: Vec<String> = IGNORED_SUFFIXES
+            .iter()
+            .map(|suffix| format!("{name}{suffix}"))
+            .filter(|pattern| !existing.lines().any(|line| line == pattern))
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        create_parent_dir(&exclude)?;
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&exclude)?;
+        if !existing.is_empty() && !existing.ends_with('\n') {
+            writeln!(file)?;
+        }
+        for pattern in missing {
+            writeln!(file, "{pattern}")?;
+        }
+
+        Ok(())
+    }
+
+    /// The data file as of `revision`, or none if it did not exist then.
+    fn read_at(&self, revision: &str) -> Result<Option<Data>, StorageError> {
+        let object = format!("{revision}:{}", self.file);
+        if !self.check(&["cat-file", "-e", &object])? {
+            return Ok(None);
+        }
+
+        Ok(Some(json::parse(&self.git(&["show", &object])?)?))
+    }
+
+    /// Merges `upstream` into the current branch. Git merges any other files
+    /// in the repository; the data file is merged task by task instead.
+    fn merge(&mut self, upstream: &str) -> Result<Vec<String>, StorageError> {
+        let base = match self.git(&["merge-base", "HEAD", upstream]) {
+            Ok(base) => self.read_at(&base)?,
+            // Histories that started apart, e.g. two clones that each ran `git init`
+            Err(_) => None,
+        };
+        let ours = self.json.load()?;
+        let theirs = self.read_at(upstream)?;
+
+        let lists = |data: &Option<Data>| {
+            data.as_ref()
+                .map(|data| data.lists.clone())
+                .unwrap_or_default()
+        };
+        let merged = merge::merge(&lists(&base), &ours.lists, &lists(&theirs));
+
+        // The workflow is merged as a whole: theirs if only they changed it
+        let workflow = match (base, theirs) {
+            (Some(base), Some(theirs)) if base.workflow == ours.workflow => theirs.workflow,
+            _ => ours.workflow,
+        };
+
+        // Undo steps address tasks by position, which the merge may have changed
+        let history = if same_lists(&merged.lists, &ours.lists) {
+            ours.history
+        } else {
+            History::default()
+        };
+
+        // Git's own merge of the data file is replaced below, so let it
+        // settle line conflicts any way it likes
+        let output = self.run(&[
+            "merge",
+            "--no-commit",
+            "--no-ff",
+            "--allow-unrelated-histories",
+            "-X",
+            "ours",
+            upstream,
+        ])?;
+        if !self.check(&["rev-parse", "--verify", "--quiet", "MERGE_HEAD"])? {
+            return Err(StorageError::Git(format!(
+                "git merge failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        self.json
+            .overwrite(&merged.lists, &ours.current, &history, &workflow)?;
+        self.git(&["add", "--", &self.file])?;
+
+        let unmerged = self.git(&["diff", "--name-only", "--diff-filter=U"])?;
+        if !unmerged.is_empty() {
+            self.git(&["merge", "--abort"])?;
+            return Err(StorageError::Git(format!(
+                "Could not merge {}, resolve it in {} and sync again",
+                unmerged.replace('\n', ", "),
+                self.dir.display()
+            )));
+        }
+
+        self.git(&[
+            "commit",
+            "--quiet",
+            "--no-edit",
+            "-m",
+            &format!("todo: merge {upstream}"),
+        ])?;
+
+        Ok(merged.conflicts)
+    }
+}
+
+impl Storage for GitStore {
+    fn path(&self) -> &Path {
+        self.json.path()
+    }
+
+    fn revision(&self) -> u64 {
+        self.json.revision()
+    }
+
+    fn load(&mut self) -> Result<Data, StorageError> {
+        self.json.load()
+    }
+
+    /// Older versions are read back from git for merging, which encryption
+    /// would get in the way of, so an encrypted file can only be reported.
+    fn is_encrypted(&self) -> Result<bool, StorageError> {
+        self.json.is_encrypted()
+    }
+
+    fn save(
+        &mut self,
+        lists: &[TaskList],
+        current: &str,
+        history: &History,
+        workflow: &Workflow,
+    ) -> Result<(), StorageError> {
+        self.json.save(lists, current, history, workflow)
+    }
+
+    fn commit(&mut self, message: &str) -> Result<(), StorageError> {
+        let status = self.git(&["status", "--porcelain", "--", &self.file])?;
+        if status.is_empty() {
+            return Ok(());
+        }
+
+        self.git(&["add", "--", &self.file])?;
+        self.git(&["commit", "--quiet", "-m", message, "--", &self.file])?;
+        Ok(())
+    }
+
+    /// Fetches `remote`, fast-forwards to it or merges it, and pushes the
+    /// result to the branch of the same name there.
+    fn sync(&mut self, remote: &str) -> Result<SyncReport, StorageError> {
+        // Saves that did not commit, e.g. switching lists, go out with the sync
+        self.commit("todo: update tasks")?;
+
+        if !self.git(&["remote"])?.lines().any(|name| name == remote) {
+            return Err(StorageError::Git(format!(
+                "No remote named {remote}, add one with `git -C {} remote add {remote} <url>`",
+                self.dir.display()
+            )));
+        }
+
+        let branch = self.git(&["symbolic-ref", "--short", "HEAD"])?;
+        let upstream = format!("{remote}/{branch}");
+        let mut report = SyncReport {
+            upstream: upstream.clone(),
+            pulled: false,
+            pushed: false,
+            conflicts: Vec::new(),
+        };
+
+        self.git(&["fetch", "--quiet", remote])?;
+
+        let has_upstream = self.check(&[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("refs/remotes/{upstream}"),
+        ])?;
+
+        if has_upstream && self.head()? != Some(self.git(&["rev-parse", &upstream])?) {
+            let behind = self.head()?.is_none()
+                || self.check(&["merge-base", "--is-ancestor", "HEAD", &upstream])?;
+            let ahead = self.check(&["merge-base", "--is-ancestor", &upstream, "HEAD"])?;
+
+            if behind {
+                self.git(&["merge", "--quiet", "--ff-only", &upstream])?;
+                report.pulled = true;
+            } else if !ahead {
+                report.conflicts = self.merge(&upstream)?;
+                report.pulled = true;
+            }
+        }
+
+        let unpushed = match self.head()? {
+            Some(head) => !has_upstream || head != self.git(&["rev-parse", &upstream])?,
+            None => false,
+        };
+        if unpushed {
+            self.git(&["push", "--quiet", "--set-upstream", remote, &branch])?;
+            report.pushed = true;
+        }
+
+        Ok(report)
+    }
+}
+
+fn same_lists(left: &[TaskList], right: &[TaskList]) -> bool {
+    serde_json::to_value(left).ok() == serde_json::to_value(right).ok()
+}
@@ -0,0 +1,369 @@
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    create_parent_dir,
+    crypto::{Cipher, Sealed},
+    Data, Storage, StorageError,
+};
+use crate::{
+    history::History,
+    lists::{TaskList, DEFAULT_LIST},
+    workflow::{self, Workflow},
+};
+
+/// Version of the on-disk layout. Bump it whenever `Task` changes shape and
+/// add a step to `migrate` that upgrades the previous version.
+pub const SCHEMA_VERSION: u64 = 9;
+
+#[derive(Serialize)]
+struct DataFileRef<'a> {
+    version: u64,
+    revision: u64,
+    lists: &'a [TaskList],
+    current: &'a str,
+    history: &'a History,
+    workflow: &'a Workflow,
+}
+
+#[derive(Deserialize)]
+struct DataFile {
+    lists: Vec<TaskList>,
+    current: String,
+    #[serde(default)]
+    history: History,
+    #[serde(default)]
+    workflow: Workflow,
+}
+
+/// An encrypted data file. The revision stays readable, so other processes
+/// can check for changes without the passphrase.
+#[derive(Serialize, Deserialize)]
+struct EncryptedFile {
+    revision: u64,
+    encrypted: Sealed,
+}
+
+/// Just the revision and whether the file is encrypted, to check for
+/// changes without parsing every task.
+#[derive(Deserialize)]
+struct Header {
+    #[serde(default)]
+    revision: u64,
+    encrypted: Option<IgnoredAny>,
+}
+
+/// Keeps everything in a single JSON file, encrypted if a passphrase was set.
+pub struct JsonStore {
+    path: PathBuf,
+    /// Counts the saves of the file; 0 before the first one.
+    revision: u64,
+    /// The passphrase the data file is encrypted with.
+    passphrase: Option<String>,
+    /// What saves encrypt with, or none to write plain JSON. Follows the
+    /// file on every load.
+    cipher: Option<Cipher>,
+}
+
+impl JsonStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            revision: 0,
+            passphrase: None,
+            cipher: None,
+        }
+    }
+
+    fn read_header(&self) -> Result<Option<Header>, StorageError> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&self.path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    fn read_revision(&self) -> Result<u64, StorageError> {
+        Ok(self
+            .read_header()?
+            .map(|header| header.revision)
+            .unwrap_or(0))
+    }
+
+    /// Decrypts an encrypted data file with the passphrase given to `unlock`.
+    fn decrypt(&mut self, value: Value) -> Result<Value, StorageError> {
+        let file: EncryptedFile = serde_json::from_value(value)?;
+        let passphrase = self.passphrase.as_deref().ok_or(StorageError::Locked)?;
+
+        let cipher = Cipher::for_sealed(self.cipher.take(), passphrase, &file.encrypted)?;
+        let plaintext = cipher.open(&file.encrypted, file.revision)?;
+        self.cipher = Some(cipher);
+
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(suffix);
+        PathBuf::from(path)
+    }
+
+    /// Saves without checking for changes by other processes, e.g. over a
+    /// file git left half merged.
+    pub fn overwrite(
+        &mut self,
+        lists: &[TaskList],
+        current: &str,
+        history: &History,
+        workflow: &Workflow,
+    ) -> Result<(), StorageError> {
+        create_parent_dir(&self.path)?;
+
+        let lock = fs::File::create(self.sibling(".lock"))?;
+        lock.lock()?;
+
+        self.write(lists, current, history, workflow)
+    }
+
+    fn write(
+        &mut self,
+        lists: &[TaskList],
+        current: &str,
+        history: &History,
+        workflow: &Workflow,
+    ) -> Result<(), StorageError> {
+        let data = DataFileRef {
+            version: SCHEMA_VERSION,
+            revision: self.revision + 1,
+            lists,
+            current,
+            history,
+            workflow,
+        };
+        let content = match &self.cipher {
+            Some(cipher) => serde_json::to_string_pretty(&EncryptedFile {
+                revision: data.revision,
+                encrypted: cipher.seal(&serde_json::to_vec(&data)?, data.revision)?,
+            })?,
+            None => serde_json::to_string_pretty(&data)?,
+        };
+
+        let tmp_path = self.sibling(".tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+
+        self.revision += 1;
+        self.passphrase = self
+            .cipher
+            .as_ref()
+            .map(|cipher| cipher.passphrase().to_string());
+        Ok(())
+    }
+}
+
+impl Storage for JsonStore {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn load(&mut self) -> Result<Data, StorageError> {
+        if !self.path.exists() {
+            self.revision = 0;
+            return Ok(Data::default());
+        }
+
+        let content = fs::read_to_string(&self.path)?;
+        let value: Value = serde_json::from_str(&content)?;
+        self.revision = value.get("revision").and_then(Value::as_u64).unwrap_or(0);
+
+        let value = if value.get("encrypted").is_some() {
+            self.decrypt(value)?
+        } else {
+            self.cipher = None;
+            value
+        };
+
+        from_value(value)
+    }
+
+    fn is_encrypted(&self) -> Result<bool, StorageError> {
+        Ok(self
+            .read_header()?
+            .is_some_and(|header| header.encrypted.is_some()))
+    }
+
+    fn unlock(&mut self, passphrase: &str) -> Result<(), StorageError> {
+        self.passphrase = Some(passphrase.to_string());
+        Ok(())
+    }
+
+    /// Derives the new key right away, with a new salt, so saves only encrypt.
+    fn set_passphrase(&mut self, passphrase: Option<&str>) -> Result<(), StorageError> {
+        self.cipher = passphrase.map(Cipher::new).transpose()?;
+        Ok(())
+    }
+
+    /// Writes to a sibling temp file and renames it over the data file, so a
+    /// crash mid-write never leaves a truncated file behind. A lock file keeps
+    /// two processes from checking the revision and writing at the same time.
+    fn save(
+        &mut self,
+        lists: &[TaskList],
+        current: &str,
+        history: &History,
+        workflow: &Workflow,
+    ) -> Result<(), StorageError> {
+        create_parent_dir(&self.path)?;
+
+        let lock = fs::File::create(self.sibling(".lock"))?;
+        lock.lock()?;
+
+        if self.read_revision()? != self.revision {
+            return Err(StorageError::Conflict);
+        }
+
+        self.write(lists, current, history, workflow)
+    }
+}
+
+/// Reads a data file of any supported version, e.g. an older copy kept by git.
+pub fn parse(content: &str) -> Result<Data, StorageError> {
+    from_value(serde_json::from_str(content)?)
+}
+
+fn from_value(value: Value) -> Result<Data, StorageError> {
+    let data: DataFile = serde_json::from_value(migrate(value)?)?;
+
+    Ok(Data {
+        lists: data.lists,
+        current: data.current,
+        history: data.history,
+        workflow: data.workflow,
+    })
+}
+
+fn migrate(mut value: Value) -> Result<Value, StorageError> {
+    let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+
+    if version == 0 || version > SCHEMA_VERSION {
+        return Err(StorageError::UnsupportedVersion(version));
+    }
+
+    if version < 2 {
+        migrate_v1_history(&mut value);
+    }
+    if version < 3 {
+        migrate_v2_lists(&mut value);
+    }
+    if version < 4 {
+        migrate_v3_states(&mut value);
+    }
+    if version < 5 {
+        migrate_v4_archive(&mut value);
+    }
+    // Version 6 marks logged work and estimates of tasks, first written by
+    // version 3 without a bump. Older versions would drop them on their next
+    // save; they default to none, so there is nothing to upgrade.
+    // Version 7 marks snoozed reminders the same way, version 8 dependencies
+    // and version 9 task ids, which `TodoApp` gives tasks stored without one.
+
+    Ok(value)
+}
+
+/// Version 2 added subtasks: history operations address a task by its
+/// `path` through the tree instead of a top-level `position`.
+fn migrate_v1_history(value: &mut Value) {
+    let Some(history) = value.get_mut("history").and_then(Value::as_object_mut) else {
+        return;
+    };
+
+    for stack in history.values_mut().filter_map(Value::as_array_mut) {
+        for operation in stack.iter_mut().filter_map(Value::as_object_mut) {
+            if let Some(position) = operation.remove("position") {
+                operation.insert("path".to_string(), Value::Array(vec![position]));
+            }
+        }
+    }
+}
+
+/// Version 3 added named lists: the single task list becomes the default
+/// list, and history operations name the list they change.
+fn migrate_v2_lists(value: &mut Value) {
+    let Some(data) = value.as_object_mut() else {
+        return;
+    };
+
+    let tasks = data.remove("tasks").unwrap_or(Value::Array(Vec::new()));
+    data.insert(
+        "lists".to_string(),
+        json!([{ "name": DEFAULT_LIST, "tasks": tasks }]),
+    );
+    data.insert("current".to_string(), json!(DEFAULT_LIST));
+
+    fn name_list(operation: &mut Value) {
+        let Some(operation) = operation.as_object_mut() else {
+            return;
+        };
+
+        match operation
+            .get_mut("operations")
+            .and_then(Value::as_array_mut)
+        {
+            Some(operations) => operations.iter_mut().for_each(name_list),
+            None => {
+                operation.insert("list".to_string(), json!(DEFAULT_LIST));
+            }
+        }
+    }
+
+    if let Some(history) = data.get_mut("history").and_then(Value::as_object_mut) {
+        for stack in history.values_mut().filter_map(Value::as_array_mut) {
+            stack.iter_mut().for_each(name_list);
+        }
+    }
+}
+
+/// Version 4 replaced the done flag of tasks with a workflow state. Tasks
+/// are found anywhere in the file, as history operations hold copies too.
+pub(super) fn migrate_v3_states(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            if let Some(is_completed) = object.remove("is_completed") {
+                let state = if is_completed.as_bool().unwrap_or(false) {
+                    workflow::DONE
+                } else {
+                    workflow::TODO
+                };
+                object.insert("state".to_string(), json!(state));
+            }
+            object.values_mut().for_each(migrate_v3_states);
+        }
+        Value::Array(items) => items.iter_mut().for_each(migrate_v3_states),
+        _ => {}
+    }
+}
+
+/// Version 5 added the archive of each list, and notes and attachments of
+/// tasks. Older versions would drop them on their next save, so they refuse
+/// the file instead; upgrading only starts every list with an empty archive.
+fn migrate_v4_archive(value: &mut Value) {
+    let Some(lists) = value.get_mut("lists").and_then(Value::as_array_mut) else {
+        return;
+    };
+
+    for list in lists.iter_mut().filter_map(Value::as_object_mut) {
+        list.entry("archive").or_insert_with(|| json!([]));
+    }
+}
@@ -0,0 +1,264 @@
+mod crypto;
+mod git;
+mod json;
+mod sqlite;
+
+use std::{
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{
+    history::History,
+    lists::{TaskList, DEFAULT_LIST},
+    workflow::Workflow,
+};
+
+pub use git::GitStore;
+pub use json::JsonStore;
+pub use sqlite::SqliteStore;
+
+const DATA_FILE_ENV: &str = "TODO_FILE";
+const DATA_DIR_NAME: &str = "todo-app";
+const DATA_FILE_STEM: &str = "tasks";
+
+#[derive(Debug)]
+pub enum StorageError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+    Sqlite(rusqlite::Error),
+    UnsupportedVersion(u64),
+    /// Another process saved the tasks after they were loaded.
+    Conflict,
+    Git(String),
+    /// `todo sync` was run on a store that is not kept in git.
+    SyncUnsupported,
+    /// The data file is encrypted and no passphrase was given.
+    Locked,
+    /// The data file could not be decrypted with the passphrase given.
+    WrongPassphrase,
+    Encryption(String),
+    /// Encryption was asked of a store other than the JSON one.
+    EncryptionUnsupported,
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Io(err) => write!(f, "{err}"),
+            StorageError::Parse(err) => write!(f, "Invalid data file: {err}"),
+            StorageError::Sqlite(err) => write!(f, "Database error: {err}"),
+            StorageError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported data file version {version}")
+            }
+            StorageError::Conflict => write!(
+                f,
+                "The tasks were changed by another todo process. They have been reloaded, please try again"
+            ),
+            StorageError::Git(err) => write!(f, "Git error: {err}"),
+            StorageError::SyncUnsupported => {
+                write!(f, "Only the git store can be synced, pick it with --store git")
+            }
+            StorageError::Locked => write!(f, "The data file is encrypted, please give its passphrase"),
+            StorageError::WrongPassphrase => write!(
+                f,
+                "Wrong passphrase, or the data file was changed outside of todo"
+            ),
+            StorageError::Encryption(err) => write!(f, "Encryption error: {err}"),
+            StorageError::EncryptionUnsupported => {
+                write!(f, "Only the json store can be encrypted")
+            }
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<io::Error> for StorageError {
+    fn from(err: io::Error) -> Self {
+        StorageError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for StorageError {
+    fn from(err: serde_json::Error) -> Self {
+        StorageError::Parse(err)
+    }
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(err: rusqlite::Error) -> Self {
+        StorageError::Sqlite(err)
+    }
+}
+
+/// Everything kept in the data file.
+pub struct Data {
+    pub lists: Vec<TaskList>,
+    /// The list commands act on unless another one is picked with `--list`.
+    pub current: String,
+    pub history: History,
+    pub workflow: Workflow,
+}
+
+impl Default for Data {
+    fn default() -> Self {
+        Self {
+            lists: vec![TaskList::new(DEFAULT_LIST)],
+            current: DEFAULT_LIST.to_string(),
+            history: History::default(),
+            workflow: Workflow::default(),
+        }
+    }
+}
+
+/// What `Storage::sync` did.
+pub struct SyncReport {
+    /// The remote branch synced with, e.g. `origin/main`.
+    pub upstream: String,
+    pub pulled: bool,
+    pub pushed: bool,
+    /// Tasks both sides changed differently, where the local change was kept.
+    pub conflicts: Vec<String>,
+}
+
+/// Where `TodoApp` keeps its lists and history.
+///
+/// A store remembers the revision it loaded last, and `save` fails with
+/// `StorageError::Conflict` instead of overwriting changes another process
+/// saved in the meantime.
+pub trait Storage {
+    fn path(&self) -> &Path;
+
+    /// The revision loaded or saved last. Every save adds one, so it tells
+    /// whether the tasks changed since a client last read them.
+    fn revision(&self) -> u64;
+
+    fn load(&mut self) -> Result<Data, StorageError>;
+
+    fn save(
+        &mut self,
+        lists: &[TaskList],
+        current: &str,
+        history: &History,
+        workflow: &Workflow,
+    ) -> Result<(), StorageError>;
+
+    /// Records the last save under `message`, for stores that keep a log of changes.
+    fn commit(&mut self, _message: &str) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    /// Whether the data file is encrypted, so `unlock` has to be given its
+    /// passphrase before `load`.
+    fn is_encrypted(&self) -> Result<bool, StorageError> {
+        Ok(false)
+    }
+
+    /// Gives the passphrase `load` decrypts the data file with.
+    fn unlock(&mut self, _passphrase: &str) -> Result<(), StorageError> {
+        Err(StorageError::EncryptionUnsupported)
+    }
+
+    /// Encrypts the data file with `passphrase` from the next save on, or
+    /// writes it in the clear again with none.
+    fn set_passphrase(&mut self, _passphrase: Option<&str>) -> Result<(), StorageError> {
+        Err(StorageError::EncryptionUnsupported)
+    }
+
+    /// Merges in the changes saved in `remote` since the last sync and sends
+    /// it the local ones.
+    fn sync(&mut self, _remote: &str) -> Result<SyncReport, StorageError> {
+        Err(StorageError::SyncUnsupported)
+    }
+}
+
+/// The kinds of data file tasks can be kept in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Json,
+    Sqlite,
+    /// A JSON file in a git working tree, committed on every change.
+    Git,
+}
+
+impl Backend {
+    /// Guesses the backend from a file extension: `.db`, `.sqlite` and `.sqlite3` are SQLite.
+    pub fn from_path(path: &Path) -> Option<Backend> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "json" => Some(Backend::Json),
+            "db" | "sqlite" | "sqlite3" => Some(Backend::Sqlite),
+            _ => None,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Backend::Json | Backend::Git => "json",
+            Backend::Sqlite => "db",
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.to_lowercase().as_str() {
+            "json" => Ok(Backend::Json),
+            "sqlite" => Ok(Backend::Sqlite),
+            "git" => Ok(Backend::Git),
+            _ => Err(format!(
+                "Invalid store: {input} (expected json, sqlite or git)"
+            )),
+        }
+    }
+}
+
+/// Opens the data file from the `--file` flag, then `TODO_FILE`, then the XDG
+/// data directory. The backend comes from `--store`, then the file extension,
+/// and is JSON otherwise.
+pub fn open(
+    flag: Option<PathBuf>,
+    backend: Option<Backend>,
+) -> Result<Box<dyn Storage>, StorageError> {
+    let path = flag.or_else(|| env::var_os(DATA_FILE_ENV).map(PathBuf::from));
+    let backend = backend
+        .or_else(|| path.as_deref().and_then(Backend::from_path))
+        .unwrap_or(Backend::Json);
+    let path = path.unwrap_or_else(|| default_path(backend));
+
+    Ok(match backend {
+        Backend::Json => Box::new(JsonStore::new(path)),
+        Backend::Sqlite => Box::new(SqliteStore::open(path)?),
+        Backend::Git => Box::new(GitStore::open(path)?),
+    })
+}
+
+fn create_parent_dir(path: &Path) -> Result<(), StorageError> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn default_path(backend: Backend) -> PathBuf {
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .unwrap_or_default();
+
+    let dir = data_home.join(DATA_DIR_NAME);
+    // The git store gets a directory of its own to be the working tree
+    let dir = match backend {
+        Backend::Git => dir.join("git"),
+        _ => dir,
+    };
+
+    dir.join(DATA_FILE_STEM).with_extension(backend.extension())
+}
@@ -0,0 +1,246 @@
+use rusqlite::{params, Connection, OptionalExtension, Transaction, TransactionBehavior};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use super::{create_parent_dir, json, Data, Storage, StorageError};
+use crate::{history::History, lists::TaskList, task::Task, workflow::Workflow};
+
+/// Applied in order on open; `PRAGMA user_version` records how many ran.
+/// Add a file for every change to the tables, or to the JSON in `tasks.data`.
+const MIGRATIONS: &[&str] = &[
+    include_str!("../../migrations/0001_init.sql"),
+    include_str!("../../migrations/0002_task_states.sql"),
+    include_str!("../../migrations/0003_archive.sql"),
+];
+
+/// How long to wait for another process to finish writing before giving up.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Task rows with their id, grouped by list, parent id and whether they are
+/// archived.
+type Children = HashMap<(String, Option<i64>, bool), Vec<(i64, Task)>>;
+
+/// Keeps lists and tasks in an SQLite database. Each save is one transaction.
+pub struct SqliteStore {
+    path: PathBuf,
+    connection: Connection,
+    /// The revision from the last load or save.
+    revision: u64,
+}
+
+impl SqliteStore {
+    pub fn open(path: PathBuf) -> Result<Self, StorageError> {
+        create_parent_dir(&path)?;
+
+        let mut connection = Connection::open(&path)?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        migrate(&mut connection)?;
+
+        Ok(Self {
+            path,
+            connection,
+            revision: 0,
+        })
+    }
+}
+
+impl Storage for SqliteStore {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn load(&mut self) -> Result<Data, StorageError> {
+        let tx = self.connection.transaction()?;
+
+        let revision = read_revision(&tx)?;
+        let current = read_meta(&tx, "current")?;
+        let history: History = serde_json::from_str(&read_meta(&tx, "history")?)?;
+        let workflow = match read_meta(&tx, "workflow")?.as_str() {
+            "" => Workflow::default(),
+            workflow => serde_json::from_str(workflow)?,
+        };
+
+        let mut names = Vec::new();
+        let mut statement = tx.prepare("SELECT name FROM lists ORDER BY position")?;
+        for name in statement.query_map([], |row| row.get::<_, String>(0))? {
+            names.push(name?);
+        }
+
+        let mut children = Children::new();
+        let mut statement =
+            tx.prepare("SELECT id, list, parent, archived, data FROM tasks ORDER BY position")?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let task: Task = serde_json::from_str(&row.get::<_, String>(4)?)?;
+            children
+                .entry((row.get(1)?, row.get(2)?, row.get(3)?))
+                .or_default()
+                .push((row.get(0)?, task));
+        }
+
+        let lists = names
+            .into_iter()
+            .map(|name| TaskList {
+                tasks: build_tree(&mut children, &name, None, false),
+                archive: build_tree(&mut children, &name, None, true),
+                name,
+            })
+            .collect();
+
+        self.revision = revision;
+        Ok(Data {
+            lists,
+            current,
+            history,
+            workflow,
+        })
+    }
+
+    fn save(
+        &mut self,
+        lists: &[TaskList],
+        current: &str,
+        history: &History,
+        workflow: &Workflow,
+    ) -> Result<(), StorageError> {
+        // Takes the write lock up front, so no other process can save between
+        // the revision check and the commit
+        let tx = self
+            .connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        if read_revision(&tx)? != self.revision {
+            return Err(StorageError::Conflict);
+        }
+
+        tx.execute("DELETE FROM tasks", [])?;
+        tx.execute("DELETE FROM lists", [])?;
+
+        for (position, list) in lists.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO lists (name, position) VALUES (?1, ?2)",
+                params![list.name, position as i64],
+            )?;
+            insert_tasks(&tx, &list.name, None, false, &list.tasks)?;
+            insert_tasks(&tx, &list.name, None, true, &list.archive)?;
+        }
+
+        let revision = self.revision + 1;
+        write_meta(&tx, "revision", &revision.to_string())?;
+        write_meta(&tx, "current", current)?;
+        write_meta(&tx, "history", &serde_json::to_string(history)?)?;
+        write_meta(&tx, "workflow", &serde_json::to_string(workflow)?)?;
+        tx.commit()?;
+
+        self.revision = revision;
+        Ok(())
+    }
+}
+
+fn migrate(connection: &mut Connection) -> Result<(), StorageError> {
+    let tx = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let version: i64 = tx.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+    if version < 0 || version as usize > MIGRATIONS.len() {
+        return Err(StorageError::UnsupportedVersion(version as u64));
+    }
+
+    for migration in &MIGRATIONS[version as usize..] {
+        tx.execute_batch(migration)?;
+    }
+    // The tasks in the history are JSON of their own, which SQL cannot reach into
+    if version < 2 {
+        let mut history: Value = serde_json::from_str(&read_meta(&tx, "history")?)?;
+        json::migrate_v3_states(&mut history);
+        write_meta(&tx, "history", &history.to_string())?;
+    }
+    tx.pragma_update(None, "user_version", MIGRATIONS.len() as i64)?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+fn read_meta(tx: &Transaction, key: &str) -> Result<String, StorageError> {
+    let value = tx
+        .query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| {
+            row.get(0)
+        })
+        .optional()?;
+
+    Ok(value.unwrap_or_default())
+}
+
+fn read_revision(tx: &Transaction) -> Result<u64, StorageError> {
+    Ok(read_meta(tx, "revision")?.parse().unwrap_or_default())
+}
+
+fn write_meta(tx: &Transaction, key: &str, value: &str) -> Result<(), StorageError> {
+    tx.execute(
+        "INSERT INTO meta (key, value) VALUES (?1, ?2)
+         ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+        [key, value],
+    )?;
+    Ok(())
+}
+
+fn insert_tasks(
+    tx: &Transaction,
+    list: &str,
+    parent: Option<i64>,
+    archived: bool,
+    tasks: &[Task],
+) -> Result<(), StorageError> {
+    for (position, task) in tasks.iter().enumerate() {
+        let fields = Task {
+            subtasks: Vec::new(),
+            ..task.clone()
+        };
+
+        tx.execute(
+            "INSERT INTO tasks (list, parent, archived, position, data)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                list,
+                parent,
+                archived,
+                position as i64,
+                serde_json::to_string(&fields)?
+            ],
+        )?;
+        insert_tasks(
+            tx,
+            list,
+            Some(tx.last_insert_rowid()),
+            false,
+            &task.subtasks,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Takes the rows below `parent` out of `children` and nests their subtasks in them.
+fn build_tree(
+    children: &mut Children,
+    list: &str,
+    parent: Option<i64>,
+    archived: bool,
+) -> Vec<Task> {
+    children
+        .remove(&(list.to_string(), parent, archived))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(id, mut task)| {
+            task.subtasks = build_tree(children, list, Some(id), false);
+            task
+        })
+        .collect()
+}
@@ -0,0 +1,404 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+use crate::{
+    dates,
+    recurrence::Recurrence,
+    timelog::{Effort, WorkInterval},
+    workflow,
+};
+
+const DUE_PREFIX: &str = "due:";
+const REPEAT_PREFIX: &str = "repeat:";
+const ESTIMATE_PREFIX: &str = "est:";
+const TAG_PREFIX: char = '+';
+const PRIORITY_PREFIX: char = '!';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+impl Priority {
+    pub fn parse(input: &str) -> Option<Priority> {
+        match input.to_lowercase().as_str() {
+            "h" | "high" => Some(Priority::High),
+            "m" | "med" | "medium" => Some(Priority::Medium),
+            "l" | "low" => Some(Priority::Low),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Priority::High => write!(f, "high"),
+            Priority::Medium => write!(f, "medium"),
+            Priority::Low => write!(f, "low"),
+        }
+    }
+}
+
+/// A due date with an optional time of day, in local time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Due {
+    pub date: NaiveDate,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<NaiveTime>,
+}
+
+impl Due {
+    /// Accepts `2026-11-01` or `2026-11-01T17:00`.
+    pub fn parse(input: &str) -> Option<Due> {
+        if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+            return Some(Due { date, time: None });
+        }
+
+        NaiveDateTime::parse_from_str(input, "%Y-%m-%dT%H:%M")
+            .ok()
+            .map(|datetime| Due {
+                date: datetime.date(),
+                time: Some(datetime.time()),
+            })
+    }
+
+    /// The moment the task becomes overdue: the given time, or the end of the day.
+    pub fn deadline(&self) -> NaiveDateTime {
+        let time = self
+            .time
+            .unwrap_or_else(|| NaiveTime::from_hms_opt(23, 59, 59).unwrap());
+
+        self.date.and_time(time)
+    }
+}
+
+impl fmt::Display for Due {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.time {
+            Some(time) => write!(f, "{}T{}", self.date, time.format("%H:%M")),
+            None => write!(f, "{}", self.date),
+        }
+    }
+}
+
+/// The description and metadata read from inline syntax such as
+/// `Deploy api due:2026-11-01 +backend !high`.
+pub struct Attributes {
+    pub description: String,
+    pub due: Option<Due>,
+    pub priority: Option<Priority>,
+    pub tags: Vec<String>,
+    pub recurrence: Option<Recurrence>,
+    pub estimate: Option<Effort>,
+}
+
+impl Attributes {
+    /// Reads the inline syntax. Due dates such as `due:tomorrow 5pm` are
+    /// resolved against `now`, the wall-clock time in the configured timezone.
+    pub fn parse(input: &str, now: NaiveDateTime) -> Result<Attributes, String> {
+        let mut words = Vec::new();
+        let mut attributes = Attributes {
+            description: String::new(),
+            due: None,
+            priority: None,
+            tags: Vec::new(),
+            recurrence: None,
+            estimate: None,
+        };
+
+        let input: Vec<&str> = input.split_whitespace().collect();
+        let mut index = 0;
+
+        while let Some(&word) = input.get(index) {
+            index += 1;
+
+            if let Some(value) = word.strip_prefix(DUE_PREFIX) {
+                let (due, taken) = dates::parse_due_words(value, &input[index..], now)
+                    .ok_or_else(|| format!("Invalid due date: {value}"))?;
+                attributes.due = Some(due);
+                index += taken;
+            } else if let Some(value) = word.strip_prefix(REPEAT_PREFIX) {
+                attributes.recurrence = Some(value.parse()?);
+            } else if let Some(value) = word.strip_prefix(ESTIMATE_PREFIX) {
+                attributes.estimate = Some(value.parse()?);
+            } else if let Some(value) = word.strip_prefix(PRIORITY_PREFIX) {
+                let priority =
+                    Priority::parse(value).ok_or_else(|| format!("Invalid priority: {value}"))?;
+                attributes.priority = Some(priority);
+            } else if let Some(tag) = word.strip_prefix(TAG_PREFIX).filter(|tag| !tag.is_empty()) {
+                if !attributes.tags.iter().any(|existing| existing == tag) {
+                    attributes.tags.push(tag.to_string());
+                }
+            } else {
+                words.push(word);
+            }
+        }
+
+        if words.is_empty() {
+            return Err("Description cannot be empty".to_string());
+        }
+
+        attributes.description = words.join(" ");
+        Ok(attributes)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    /// Stays the same when the task moves, so other tasks can refer to it.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
+    pub description: String,
+    /// Where the task is in the workflow, `done` once completed.
+    #[serde(default = "todo_state")]
+    pub state: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<Due>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<Recurrence>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<Effort>,
+    /// Logged work, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub work: Vec<WorkInterval>,
+    /// Ids of the tasks that have to be done before this one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// Reminders for the task are held back until then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snoozed_until: Option<DateTime<Local>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Local>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime<Local>>,
+    /// Longer context in Markdown, edited in `$EDITOR`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub note: String,
+    /// URLs and absolute paths of files the task refers to. The files are
+    /// not copied, only pointed at.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subtasks: Vec<Task>,
+}
+
+impl Task {
+    pub fn new(attributes: Attributes) -> Self {
+        Self {
+            id: new_id(),
+            description: attributes.description,
+            state: workflow::TODO.to_string(),
+            due: attributes.due,
+            priority: attributes.priority,
+            tags: attributes.tags,
+            recurrence: attributes.recurrence,
+            estimate: attributes.estimate,
+            work: Vec::new(),
+            depends_on: Vec::new(),
+            snoozed_until: None,
+            created_at: Some(Local::now()),
+            completed_at: None,
+            note: String::new(),
+            attachments: Vec::new(),
+            subtasks: Vec::new(),
+        }
+    }
+
+    /// Replaces the description, and any metadata given inline, leaving the rest untouched.
+    pub fn update(&mut self, attributes: Attributes) {
+        self.description = attributes.description;
+
+        if attributes.due.is_some() {
+            self.due = attributes.due;
+            self.snoozed_until = None;
+        }
+        if attributes.priority.is_some() {
+            self.priority = attributes.priority;
+        }
+        if !attributes.tags.is_empty() {
+            self.tags = attributes.tags;
+        }
+        if attributes.recurrence.is_some() {
+            self.recurrence = attributes.recurrence;
+        }
+        if attributes.estimate.is_some() {
+            self.estimate = attributes.estimate;
+        }
+    }
+
+    /// Replaces the description and all inline metadata, clearing whatever
+    /// `attributes` leaves out. For editing the whole of `inline()`.
+    pub fn replace(&mut self, attributes: Attributes) {
+        if attributes.due != self.due {
+            self.snoozed_until = None;
+        }
+        self.description = attributes.description;
+        self.due = attributes.due;
+        self.priority = attributes.priority;
+        self.tags = attributes.tags;
+        self.recurrence = attributes.recurrence;
+        self.estimate = attributes.estimate;
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.state == workflow::DONE
+    }
+
+    /// Completes the task together with all of its subtasks, stopping their timers.
+    pub fn complete(&mut self) {
+        if !self.is_completed() {
+            self.state = workflow::DONE.to_string();
+            self.completed_at = Some(Local::now());
+        }
+        self.stop(Local::now());
+
+        for subtask in &mut self.subtasks {
+            subtask.complete();
+        }
+    }
+
+    /// Reopens the task together with all of its subtasks.
+    pub fn reopen(&mut self) {
+        self.state = workflow::TODO.to_string();
+        self.completed_at = None;
+
+        for subtask in &mut self.subtasks {
+            subtask.reopen();
+        }
+    }
+
+    /// A task with subtasks is done exactly when all of them are done.
+    pub fn sync_completion(&mut self) {
+        if self.subtasks.is_empty() {
+            return;
+        }
+
+        let all_done = self.subtasks.iter().all(Task::is_completed);
+
+        if all_done && !self.is_completed() {
+            self.state = workflow::DONE.to_string();
+            self.completed_at = Some(Local::now());
+        } else if !all_done && self.is_completed() {
+            self.state = workflow::TODO.to_string();
+            self.completed_at = None;
+        }
+    }
+
+    /// Moves the task to an open state of the workflow. A completed task is
+    /// reopened first, with its subtasks.
+    pub fn set_state(&mut self, state: &str) {
+        if self.is_completed() {
+            self.reopen();
+        }
+        self.state = state.to_string();
+    }
+
+    /// Writes the task back in the inline syntax accepted by `Attributes::parse`.
+    pub fn inline(&self) -> String {
+        let mut words = vec![self.description.clone()];
+
+        if let Some(due) = self.due {
+            words.push(format!("{DUE_PREFIX}{due}"));
+        }
+        if let Some(priority) = self.priority {
+            words.push(format!("{PRIORITY_PREFIX}{priority}"));
+        }
+        for tag in &self.tags {
+            words.push(format!("{TAG_PREFIX}{tag}"));
+        }
+        if let Some(recurrence) = &self.recurrence {
+            words.push(format!("{REPEAT_PREFIX}{recurrence}"));
+        }
+        if let Some(estimate) = self.estimate {
+            words.push(format!("{ESTIMATE_PREFIX}{estimate}"));
+        }
+
+        words.join(" ")
+    }
+
+    /// The open copy of a recurring task that follows this one, due at the
+    /// first occurrence after `today`. Without a due date the rule counts from `today`.
+    pub fn next_occurrence(&self, today: NaiveDate) -> Option<Task> {
+        let recurrence = self.recurrence.as_ref()?;
+        let mut date = recurrence.next(self.due.map_or(today, |due| due.date));
+
+        // Skip occurrences that were missed while the task was overdue
+        while date <= today {
+            date = recurrence.next(date);
+        }
+
+        let mut next = self.clone();
+        next.reopen();
+        next.id = new_id();
+        next.created_at = Some(Local::now());
+        next.work.clear();
+        next.snoozed_until = None;
+        next.due = Some(Due {
+            date,
+            time: self.due.and_then(|due| due.time),
+        });
+
+        Some(next)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.work.iter().any(|interval| interval.end.is_none())
+    }
+
+    /// Starts the timer, unless it is already running.
+    pub fn start(&mut self, now: DateTime<Local>) {
+        if !self.is_running() {
+            self.work.push(WorkInterval {
+                start: now,
+                end: None,
+            });
+        }
+    }
+
+    /// Stops the timer if it is running.
+    pub fn stop(&mut self, now: DateTime<Local>) {
+        for interval in self
+            .work
+            .iter_mut()
+            .filter(|interval| interval.end.is_none())
+        {
+            interval.end = Some(now.max(interval.start));
+        }
+    }
+
+    /// Total time logged on the task, counting a running timer until `now`.
+    pub fn spent(&self, now: DateTime<Local>) -> TimeDelta {
+        self.work
+            .iter()
+            .map(|interval| interval.end.unwrap_or(now) - interval.start)
+            .sum()
+    }
+
+    pub fn is_overdue(&self, now: NaiveDateTime) -> bool {
+        !self.is_completed() && self.due.is_some_and(|due| due.deadline() < now)
+    }
+}
+
+fn todo_state() -> String {
+    workflow::TODO.to_string()
+}
+
+pub fn new_id() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+/// Whether an attachment is a URL such as `https://…` or `mailto:…` rather
+/// than a file path.
+pub fn is_url(attachment: &str) -> bool {
+    attachment.contains("://") || attachment.starts_with("mailto:")
+}
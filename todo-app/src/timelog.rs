@@ -0,0 +1,178 @@
+use chrono::{DateTime, Days, Local, NaiveDate, TimeDelta, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use crate::task::Task;
+
+/// Upper bound on an estimate or logged entry, so a typo cannot book years of work.
+const MAX_MINUTES: u32 = 1000 * 60;
+
+/// A length of time in whole minutes, written as `90m`, `2h` or `1h30m`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Effort(u32);
+
+impl Effort {
+    pub fn delta(&self) -> TimeDelta {
+        TimeDelta::minutes(self.0 as i64)
+    }
+}
+
+impl FromStr for Effort {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid duration: {input} (e.g. 45m, 2h or 1h30m)");
+        let lower = input.to_lowercase();
+
+        let (hours, rest) = match lower.split_once('h') {
+            Some((hours, rest)) => (hours.parse::<u32>().map_err(|_| invalid())?, rest),
+            None => (0, lower.as_str()),
+        };
+        let minutes = match rest.strip_suffix('m') {
+            Some(minutes) => minutes.parse::<u32>().map_err(|_| invalid())?,
+            None if rest.is_empty() => 0,
+            None => return Err(invalid()),
+        };
+
+        match hours
+            .checked_mul(60)
+            .and_then(|hours| hours.checked_add(minutes))
+        {
+            Some(total) if total > 0 && total <= MAX_MINUTES => Ok(Effort(total)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Effort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format_delta(self.delta()))
+    }
+}
+
+impl TryFrom<String> for Effort {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Effort> for String {
+    fn from(effort: Effort) -> Self {
+        effort.to_string()
+    }
+}
+
+/// A stretch of work on a task. Without an `end` the timer is still running.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkInterval {
+    pub start: DateTime<Local>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<DateTime<Local>>,
+}
+
+impl WorkInterval {
+    /// How much of the interval lies between `from` and `to`; a running one counts until `now`.
+    fn overlap(
+        &self,
+        from: DateTime<Local>,
+        to: DateTime<Local>,
+        now: DateTime<Local>,
+    ) -> TimeDelta {
+        let start = self.start.max(from);
+        let end = self.end.unwrap_or(now).min(to);
+
+        (end - start).max(TimeDelta::zero())
+    }
+}
+
+/// Time spent per task, tag and day, over a range of days.
+pub struct Report {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Label, time spent in the range and estimate of every task worked on.
+    pub tasks: Vec<(String, TimeDelta, Option<Effort>)>,
+    pub tags: BTreeMap<String, TimeDelta>,
+    pub days: BTreeMap<NaiveDate, TimeDelta>,
+    pub total: TimeDelta,
+}
+
+impl Report {
+    /// Sums the work logged on `tasks` between the start of `from` and the end of `to`.
+    pub fn new<'a>(
+        tasks: impl IntoIterator<Item = (String, &'a Task)>,
+        from: NaiveDate,
+        to: NaiveDate,
+        now: DateTime<Local>,
+    ) -> Self {
+        let mut report = Report {
+            from,
+            to,
+            tasks: Vec::new(),
+            tags: BTreeMap::new(),
+            days: BTreeMap::new(),
+            total: TimeDelta::zero(),
+        };
+
+        let days: Vec<NaiveDate> = from.iter_days().take_while(|day| *day <= to).collect();
+
+        for (label, task) in tasks {
+            let mut spent = TimeDelta::zero();
+
+            for day in &days {
+                let (Some(start), Some(end)) = (
+                    start_of_day(*day),
+                    day.checked_add_days(Days::new(1)).and_then(start_of_day),
+                ) else {
+                    continue;
+                };
+
+                let on_day: TimeDelta = task
+                    .work
+                    .iter()
+                    .map(|interval| interval.overlap(start, end, now))
+                    .sum();
+
+                if !on_day.is_zero() {
+                    *report.days.entry(*day).or_default() += on_day;
+                    spent += on_day;
+                }
+            }
+
+            if spent.is_zero() {
+                continue;
+            }
+
+            if task.tags.is_empty() {
+                *report.tags.entry("(untagged)".to_string()).or_default() += spent;
+            }
+            for tag in &task.tags {
+                *report.tags.entry(format!("+{tag}")).or_default() += spent;
+            }
+
+            report.total += spent;
+            report.tasks.push((label, spent, task.estimate));
+        }
+
+        report
+    }
+}
+
+/// Writes a duration as `1h05m`, or `25m` below an hour.
+pub fn format_delta(delta: TimeDelta) -> String {
+    let minutes = delta.num_minutes();
+
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{minutes}m"),
+        (hours, 0) => format!("{hours}h"),
+        (hours, minutes) => format!("{hours}h{minutes:02}m"),
+    }
+}
+
+pub fn start_of_day(date: NaiveDate) -> Option<DateTime<Local>> {
+    Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .earliest()
+}
@@ -0,0 +1,320 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use crate::task::{new_id, Task};
+
+/// Shortest id prefix shown and accepted, longer where needed to tell tasks apart.
+pub const MIN_ID_LEN: usize = 4;
+
+/// Where a task sits in the tree of tasks and subtasks.
+///
+/// Holds 0-based positions, one per level, but is written and parsed as the
+/// dotted 1-based index shown by `list_items`, e.g. `2.1`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TaskPath(Vec<usize>);
+
+impl TaskPath {
+    pub fn top(position: usize) -> Self {
+        TaskPath(vec![position])
+    }
+
+    pub fn child(&self, position: usize) -> Self {
+        let mut positions = self.0.clone();
+        positions.push(position);
+        TaskPath(positions)
+    }
+
+    /// The position right after this task among its siblings.
+    pub fn next_sibling(&self) -> Self {
+        let mut positions = self.0.clone();
+        if let Some(last) = positions.last_mut() {
+            *last += 1;
+        }
+        TaskPath(positions)
+    }
+
+    pub fn parent(&self) -> Option<TaskPath> {
+        match self.0.len() {
+            0 | 1 => None,
+            len => Some(TaskPath(self.0[..len - 1].to_vec())),
+        }
+    }
+
+    /// Position of the task among its siblings.
+    pub fn last(&self) -> usize {
+        self.0.last().copied().unwrap_or_default()
+    }
+
+    /// Whether `other` is a subtask of this task, at any depth.
+    pub fn is_ancestor_of(&self, other: &TaskPath) -> bool {
+        other.0.len() > self.0.len() && other.0.starts_with(&self.0)
+    }
+
+    /// Nesting level, 0 for top-level tasks.
+    pub fn depth(&self) -> usize {
+        self.0.len().saturating_sub(1)
+    }
+}
+
+impl fmt::Display for TaskPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let labels: Vec<String> = self
+            .0
+            .iter()
+            .map(|position| (position + 1).to_string())
+            .collect();
+
+        write!(f, "{}", labels.join("."))
+    }
+}
+
+impl FromStr for TaskPath {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        input
+            .trim()
+            .split('.')
+            .map(|part| match part.parse::<usize>() {
+                Ok(index) if index > 0 => Ok(index - 1),
+                _ => Err(format!("Invalid task index: {input}")),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(TaskPath)
+    }
+}
+
+/// How a task is picked on the command line: by its dotted index in the
+/// current list, or by the start of its id in any list, e.g. `#3fa9`. The
+/// `#` can be left out unless the id starts with digits only.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskRef {
+    Path(TaskPath),
+    Id(String),
+}
+
+impl fmt::Display for TaskRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskRef::Path(path) => write!(f, "{path}"),
+            TaskRef::Id(id) => write!(f, "#{id}"),
+        }
+    }
+}
+
+impl FromStr for TaskRef {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+
+        let id = match input.strip_prefix('#') {
+            Some(id) => id,
+            None => match input.parse::<TaskPath>() {
+                Ok(path) => return Ok(TaskRef::Path(path)),
+                Err(_) => input,
+            },
+        };
+
+        if id.len() >= MIN_ID_LEN && id.chars().all(|c| c.is_ascii_hexdigit()) {
+            Ok(TaskRef::Id(id.to_lowercase()))
+        } else {
+            Err(format!(
+                "Invalid task: {input} (expected an index such as 2.1 or an id such as #3fa9)"
+            ))
+        }
+    }
+}
+
+pub fn find<'a>(items: &'a [Task], path: &TaskPath) -> Option<&'a Task> {
+    let (first, rest) = path.0.split_first()?;
+
+    rest.iter().try_fold(items.get(*first)?, |task, position| {
+        task.subtasks.get(*position)
+    })
+}
+
+pub fn find_mut<'a>(items: &'a mut [Task], path: &TaskPath) -> Option<&'a mut Task> {
+    let (first, rest) = path.0.split_first()?;
+
+    rest.iter()
+        .try_fold(items.get_mut(*first)?, |task, position| {
+            task.subtasks.get_mut(*position)
+        })
+}
+
+/// The list holding the task at `path` (and its siblings).
+pub fn siblings_mut<'a>(items: &'a mut Vec<Task>, path: &TaskPath) -> Option<&'a mut Vec<Task>> {
+    match path.parent() {
+        Some(parent) => find_mut(items, &parent).map(|task| &mut task.subtasks),
+        None => Some(items),
+    }
+}
+
+/// All tasks in display order, parents before their subtasks.
+pub fn flatten(items: &[Task]) -> Vec<(TaskPath, &Task)> {
+    fn walk<'a>(tasks: &'a [Task], parent: Option<&TaskPath>, out: &mut Vec<(TaskPath, &'a Task)>) {
+        for (position, task) in tasks.iter().enumerate() {
+            let path = match parent {
+                Some(parent) => parent.child(position),
+                None => TaskPath::top(position),
+            };
+
+            out.push((path.clone(), task));
+            walk(&task.subtasks, Some(&path), out);
+        }
+    }
+
+    let mut out = Vec::new();
+    walk(items, None, &mut out);
+    out
+}
+
+/// Re-derives the completion of every ancestor of `path`, deepest first,
+/// after one of its descendants changed.
+pub fn sync_ancestors(items: &mut [Task], path: &TaskPath) {
+    let mut current = path.parent();
+
+    while let Some(parent) = current {
+        if let Some(task) = find_mut(items, &parent) {
+            task.sync_completion();
+        }
+        current = parent.parent();
+    }
+}
+
+/// Re-derives the completion of every task with subtasks, e.g. after an import.
+pub fn sync_all(items: &mut [Task]) {
+    for task in items {
+        sync_all(&mut task.subtasks);
+        task.sync_completion();
+    }
+}
+
+/// Gives every task without an id a new one, e.g. tasks saved before ids
+/// existed. Returns whether any task changed.
+pub fn assign_ids(items: &mut [Task]) -> bool {
+    let mut changed = false;
+
+    for task in items {
+        if task.id.is_empty() {
+            task.id = new_id();
+            changed = true;
+        }
+        changed |= assign_ids(&mut task.subtasks);
+    }
+
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::*;
+    use crate::task::Attributes;
+
+    fn task(description: &str, subtasks: Vec<Task>) -> Task {
+        let mut task = Task::new(Attributes::parse(description, NaiveDateTime::default()).unwrap());
+        task.subtasks = subtasks;
+        task
+    }
+
+    /// Plan, with Design and Build, Build with Backend; then Ship.
+    fn tasks() -> Vec<Task> {
+        vec![
+            task(
+                "Plan",
+                vec![
+                    task("Design", vec![]),
+                    task("Build", vec![task("Backend", vec![])]),
+                ],
+            ),
+            task("Ship", vec![]),
+        ]
+    }
+
+    fn path(input: &str) -> TaskPath {
+        input.parse().unwrap()
+    }
+
+    #[test]
+    fn paths_read_and_print_one_based() {
+        assert_eq!(path("2.1.3"), TaskPath::top(1).child(0).child(2));
+        assert_eq!(path(" 1.2 ").to_string(), "1.2");
+        for input in ["0", "1.0", "1.", ".1", "1-2", "x", ""] {
+            assert!(input.parse::<TaskPath>().is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn ancestors_and_siblings() {
+        assert!(path("1").is_ancestor_of(&path("1.2.1")));
+        assert!(!path("1.2").is_ancestor_of(&path("1.2")));
+        assert!(!path("1").is_ancestor_of(&path("2.1")));
+        assert_eq!(path("1.2.1").parent(), Some(path("1.2")));
+        assert_eq!(path("1").parent(), None);
+        assert_eq!(path("1.1").next_sibling(), path("1.2"));
+        assert_eq!(path("1.2.1").depth(), 2);
+    }
+
+    #[test]
+    fn find_walks_the_subtree() {
+        let tasks = tasks();
+
+        assert_eq!(find(&tasks, &path("1.2.1")).unwrap().description, "Backend");
+        assert!(find(&tasks, &path("1.3")).is_none());
+        assert!(find(&tasks, &path("2.1")).is_none());
+    }
+
+    #[test]
+    fn flatten_lists_parents_first() {
+        let tasks = tasks();
+
+        let paths: Vec<String> = flatten(&tasks)
+            .iter()
+            .map(|(path, task)| format!("{path} {}", task.description))
+            .collect();
+
+        assert_eq!(
+            paths,
+            [
+                "1 Plan",
+                "1.1 Design",
+                "1.2 Build",
+                "1.2.1 Backend",
+                "2 Ship"
+            ]
+        );
+    }
+
+    #[test]
+    fn ancestors_follow_their_subtasks() {
+        let mut tasks = tasks();
+        find_mut(&mut tasks, &path("1.1")).unwrap().complete();
+        find_mut(&mut tasks, &path("1.2.1")).unwrap().complete();
+
+        sync_ancestors(&mut tasks, &path("1.2.1"));
+        assert!(find(&tasks, &path("1.2")).unwrap().is_completed());
+        assert!(tasks[0].is_completed());
+
+        find_mut(&mut tasks, &path("1.2.1")).unwrap().reopen();
+        sync_ancestors(&mut tasks, &path("1.2.1"));
+        assert!(!tasks[0].is_completed());
+    }
+
+    #[test]
+    fn task_refs_tell_indices_from_ids() {
+        let parse = |input: &str| input.parse::<TaskRef>();
+
+        assert_eq!(parse("2.1"), Ok(TaskRef::Path(path("2.1"))));
+        assert_eq!(parse("#3FA9"), Ok(TaskRef::Id("3fa9".to_string())));
+        assert_eq!(parse("cafe"), Ok(TaskRef::Id("cafe".to_string())));
+        // Digits are an index unless marked as an id
+        assert_eq!(parse("1234"), Ok(TaskRef::Path(path("1234"))));
+        assert_eq!(parse("#1234"), Ok(TaskRef::Id("1234".to_string())));
+        assert!(parse("#3fa").is_err());
+        assert!(parse("coffee").is_err());
+    }
+}
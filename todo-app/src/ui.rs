@@ -0,0 +1,631 @@
+use chrono::{Local, NaiveDateTime};
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::{io, time::Duration};
+use tui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    Frame, Terminal,
+};
+
+use crate::{editor, output::time_label, reminders};
+use todo_core::{
+    dependencies::TaskIndex,
+    query::Query,
+    task::{Priority, Task},
+    timelog,
+    tree::{self, TaskPath},
+    workflow, TodoApp, TodoError,
+};
+
+const PAGE_SIZE: usize = 10;
+const HELP: &str =
+    "j/k move  space toggle  >/< next/previous state  X done anyway  a add  A add subtask  e edit  n note  d delete  t timer  z snooze  l next list  / filter  u undo  r redo  q quit";
+
+enum Mode {
+    Normal,
+    Filter,
+    Add(Option<TaskPath>),
+    Edit(TaskPath),
+}
+
+struct UiState {
+    mode: Mode,
+    list_state: ListState,
+    input: String,
+    filter: String,
+    query: Query,
+    message: Option<(String, Color)>,
+    /// Task whose note is opened in the editor before the next draw.
+    note: Option<TaskPath>,
+    should_quit: bool,
+}
+
+impl UiState {
+    fn new() -> Self {
+        let mut list_state = ListState::default();
+        list_state.select(Some(0));
+
+        Self {
+            mode: Mode::Normal,
+            list_state,
+            input: String::new(),
+            filter: String::new(),
+            query: Query::default(),
+            message: None,
+            note: None,
+            should_quit: false,
+        }
+    }
+
+    /// Paths of the tasks currently shown, in display order.
+    fn visible(&self, todo_app: &TodoApp, now: NaiveDateTime) -> Vec<TaskPath> {
+        self.query
+            .apply(todo_app.items(), now)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect()
+    }
+
+    fn selected(&self, visible: &[TaskPath]) -> Option<TaskPath> {
+        self.list_state
+            .selected()
+            .and_then(|row| visible.get(row).cloned())
+    }
+
+    fn move_selection(&mut self, offset: isize, len: usize) {
+        if len == 0 {
+            self.list_state.select(None);
+            return;
+        }
+
+        let row = self.list_state.selected().unwrap_or(0) as isize + offset;
+        self.list_state
+            .select(Some(row.clamp(0, len as isize - 1) as usize));
+    }
+
+    fn report(&mut self, result: Result<String, TodoError>) {
+        self.message = Some(match result {
+            Ok(msg) => (msg, Color::Green),
+            Err(err) => (err.to_string(), Color::Red),
+        });
+    }
+}
+
+/// Runs the full-screen interface until the user quits.
+pub fn run(mut todo_app: TodoApp) -> Result<(), Box<dyn std::error::Error>> {
+    // Terminal setup
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+    terminal.clear()?;
+
+    let result = event_loop(&mut terminal, &mut todo_app);
+
+    // Cleanup
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    result
+}
+
+fn event_loop(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    todo_app: &mut TodoApp,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut state = UiState::new();
+
+    while !state.should_quit {
+        let now = todo_app.now();
+        let visible = state.visible(todo_app, now);
+
+        // Keep the selection on a row after tasks disappear
+        if state.selected(&visible).is_none() {
+            state.move_selection(0, visible.len());
+        }
+
+        terminal.draw(|f| draw(f, todo_app, &mut state, &visible, now))?;
+
+        if event::poll(Duration::from_millis(250))? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    handle_key(todo_app, &mut state, &visible, key);
+                }
+            }
+        }
+
+        if let Some(path) = state.note.take() {
+            let result = edit_note(terminal, todo_app, &path);
+            state.report(result);
+        }
+    }
+
+    Ok(())
+}
+
+/// Hands the terminal to the editor for the note of the task at `path`,
+/// and takes it back once the editor exits.
+fn edit_note(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    todo_app: &mut TodoApp,
+    path: &TaskPath,
+) -> Result<String, TodoError> {
+    let note = todo_app.task(path)?.note.clone();
+
+    disable_raw_mode().map_err(TodoError::Io)?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen).map_err(TodoError::Io)?;
+    let edited = editor::edit(&note);
+    enable_raw_mode().map_err(TodoError::Io)?;
+    execute!(terminal.backend_mut(), EnterAlternateScreen).map_err(TodoError::Io)?;
+    terminal.clear().map_err(TodoError::Io)?;
+
+    if todo_app.set_note(path, &edited?)? {
+        Ok(format!("Saved the note of task {path}"))
+    } else {
+        Ok(format!("The note of task {path} did not change"))
+    }
+}
+
+fn handle_key(todo_app: &mut TodoApp, state: &mut UiState, visible: &[TaskPath], key: KeyEvent) {
+    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+        state.should_quit = true;
+        return;
+    }
+
+    match state.mode {
+        Mode::Normal => handle_normal_key(todo_app, state, visible, key.code),
+        Mode::Filter => match key.code {
+            KeyCode::Enter => state.mode = Mode::Normal,
+            KeyCode::Esc => {
+                state.filter.clear();
+                state.query = Query::default();
+                state.mode = Mode::Normal;
+            }
+            KeyCode::Backspace => {
+                state.filter.pop();
+                update_filter(state);
+            }
+            KeyCode::Char(c) => {
+                state.filter.push(c);
+                update_filter(state);
+            }
+            _ => {}
+        },
+        Mode::Add(_) | Mode::Edit(_) => match key.code {
+            KeyCode::Enter => {
+                let input = state.input.trim().to_string();
+                let result = match &state.mode {
+                    Mode::Edit(path) => todo_app
+                        .replace_inline(path, &input)
+                        .map(|_| "Task updated".to_string()),
+                    Mode::Add(parent) => todo_app
+                        .add(&input, parent.as_ref())
+                        .map(|path| format!("Task {path} added")),
+                    _ => return,
+                };
+
+                if result.is_ok() {
+                    state.input.clear();
+                    state.mode = Mode::Normal;
+                }
+                state.report(result);
+            }
+            KeyCode::Esc => {
+                state.input.clear();
+                state.mode = Mode::Normal;
+            }
+            KeyCode::Backspace => {
+                state.input.pop();
+            }
+            KeyCode::Char(c) => state.input.push(c),
+            _ => {}
+        },
+    }
+}
+
+fn handle_normal_key(
+    todo_app: &mut TodoApp,
+    state: &mut UiState,
+    visible: &[TaskPath],
+    code: KeyCode,
+) {
+    let selected = state.selected(visible);
+    state.message = None;
+
+    match code {
+        KeyCode::Char('q') | KeyCode::Esc => state.should_quit = true,
+        KeyCode::Down | KeyCode::Char('j') => state.move_selection(1, visible.len()),
+        KeyCode::Up | KeyCode::Char('k') => state.move_selection(-1, visible.len()),
+        KeyCode::PageDown => state.move_selection(PAGE_SIZE as isize, visible.len()),
+        KeyCode::PageUp => state.move_selection(-(PAGE_SIZE as isize), visible.len()),
+        KeyCode::Home | KeyCode::Char('g') => state.move_selection(isize::MIN / 2, visible.len()),
+        KeyCode::End | KeyCode::Char('G') => state.move_selection(isize::MAX / 2, visible.len()),
+        KeyCode::Char('/') => state.mode = Mode::Filter,
+        KeyCode::Char('a') => {
+            state.input.clear();
+            state.mode = Mode::Add(None);
+        }
+        KeyCode::Char('A') if selected.is_some() => {
+            state.input.clear();
+            state.mode = Mode::Add(selected);
+        }
+        KeyCode::Char('e') => {
+            if let Some(path) = selected {
+                if let Some(task) = tree::find(todo_app.items(), &path) {
+                    state.input = task.inline();
+                    state.mode = Mode::Edit(path);
+                }
+            }
+        }
+        KeyCode::Char('n') => state.note = selected,
+        KeyCode::Char(' ') => {
+            if let Some(path) = selected {
+                let result = todo_app.toggle(&path).map(|_| "Task updated".to_string());
+                state.report(result);
+            }
+        }
+        KeyCode::Char('X') => {
+            if let Some(path) = selected {
+                let result = todo_app
+                    .complete(&path, true)
+                    .map(|_| "Task marked as done".to_string());
+                state.report(result);
+            }
+        }
+        KeyCode::Char('>') => {
+            if let Some(path) = selected {
+                let result = todo_app
+                    .advance(&path, false)
+                    .map(|state| format!("Moved task {path} to {state}"));
+                state.report(result);
+            }
+        }
+        KeyCode::Char('<') => {
+            if let Some(path) = selected {
+                let result = todo_app
+                    .retreat(&path)
+                    .map(|state| format!("Moved task {path} back to {state}"));
+                state.report(result);
+            }
+        }
+        KeyCode::Char('d') | KeyCode::Delete => {
+            if let Some(path) = selected {
+                let result = todo_app
+                    .remove(&path)
+                    .map(|task| format!("Deleted task: {}", task.description));
+                state.report(result);
+            }
+        }
+        KeyCode::Char('t') => {
+            if let Some(path) = selected {
+                let result = match tree::find(todo_app.items(), &path) {
+                    Some(task) if task.is_running() => todo_app
+                        .stop_timer(Some(&path))
+                        .map(|_| format!("Stopped timer of task {path}")),
+                    _ => todo_app
+                        .start_timer(&path)
+                        .map(|_| format!("Started timer of task {path}")),
+                };
+                state.report(result);
+            }
+        }
+        KeyCode::Char('z') => {
+            if let Some(path) = selected {
+                let result = reminders::DEFAULT_SNOOZE
+                    .parse()
+                    .map_err(TodoError::InvalidInput)
+                    .and_then(|effort| todo_app.snooze(&path, effort))
+                    .map(|_| format!("Snoozed task {path} for {}", reminders::DEFAULT_SNOOZE));
+                state.report(result);
+            }
+        }
+        KeyCode::Char('l') => {
+            let position = todo_app
+                .lists()
+                .iter()
+                .position(|list| list.name == todo_app.current())
+                .unwrap_or_default();
+            let next = todo_app.lists()[(position + 1) % todo_app.lists().len()]
+                .name
+                .clone();
+            let result = todo_app
+                .switch_list(&next)
+                .map(|_| format!("Switched to list {next}"));
+            state.list_state.select(Some(0));
+            state.report(result);
+        }
+        KeyCode::Char('u') => {
+            let result = todo_app
+                .undo()
+                .map(|operation| format!("Undid {}", operation.describe()));
+            state.report(result);
+        }
+        KeyCode::Char('r') => {
+            let result = todo_app
+                .redo()
+                .map(|operation| format!("Redid {}", operation.describe()));
+            state.report(result);
+        }
+        _ => {}
+    }
+}
+
+fn update_filter(state: &mut UiState) {
+    match Query::parse(&state.filter, None) {
+        Ok(query) => {
+            state.query = query;
+            state.message = None;
+        }
+        // Keep the last valid query while the filter is being typed
+        Err(err) => state.message = Some((err, Color::Red)),
+    }
+}
+
+fn draw(
+    f: &mut Frame<CrosstermBackend<io::Stdout>>,
+    todo_app: &TodoApp,
+    state: &mut UiState,
+    visible: &[TaskPath],
+    now: NaiveDateTime,
+) {
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(3),
+                Constraint::Min(5),
+                Constraint::Length(3),
+            ]
+            .as_ref(),
+        )
+        .split(f.size());
+
+    let body = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+        .split(layout[1]);
+
+    // Filter bar
+    let filter_style = match state.mode {
+        Mode::Filter => Style::default().fg(Color::Yellow),
+        _ => Style::default(),
+    };
+    let filter = Paragraph::new(state.filter.as_str())
+        .style(filter_style)
+        .block(Block::default().title("Filter (/)").borders(Borders::ALL));
+    f.render_widget(filter, layout[0]);
+
+    // Task list
+    let index = TaskIndex::new(todo_app.lists());
+    let items: Vec<ListItem> = visible
+        .iter()
+        .filter_map(|path| {
+            tree::find(todo_app.items(), path).map(|task| {
+                let blocked_by = todo_app.blocked_by(&index, task);
+                ListItem::new(task_spans(path, task, now, &blocked_by))
+            })
+        })
+        .collect();
+    let title = format!(
+        "Tasks: {} ({}/{})",
+        todo_app.current(),
+        visible.len(),
+        todo_app.items().len()
+    );
+    let list = List::new(items)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(list, body[0], &mut state.list_state);
+
+    // Detail pane
+    let detail = state
+        .selected(visible)
+        .and_then(|path| tree::find(todo_app.items(), &path))
+        .map(|task| detail_lines(task, index.short_id(task)))
+        .unwrap_or_default();
+    let detail = Paragraph::new(detail)
+        .wrap(Wrap { trim: false })
+        .block(Block::default().title("Details").borders(Borders::ALL));
+    f.render_widget(detail, body[1]);
+
+    // Input or status line
+    match state.mode {
+        Mode::Add(_) | Mode::Edit(_) => {
+            let title = match &state.mode {
+                Mode::Add(None) => "New task (Enter to save, Esc to cancel)".to_string(),
+                Mode::Add(Some(parent)) => {
+                    format!("New subtask of {parent} (Enter to save, Esc to cancel)")
+                }
+                _ => "Edit task (Enter to save, Esc to cancel)".to_string(),
+            };
+            let input = Paragraph::new(state.input.as_str())
+                .block(Block::default().title(title).borders(Borders::ALL));
+            f.render_widget(input, layout[2]);
+            set_input_cursor(f, layout[2], state.input.chars().count());
+        }
+        _ => {
+            let status = match &state.message {
+                Some((msg, color)) => Span::styled(msg.clone(), Style::default().fg(*color)),
+                None => Span::raw(HELP),
+            };
+            let status =
+                Paragraph::new(Spans::from(status)).block(Block::default().borders(Borders::ALL));
+            f.render_widget(status, layout[2]);
+
+            if let Mode::Filter = state.mode {
+                set_input_cursor(f, layout[0], state.filter.chars().count());
+            }
+        }
+    }
+}
+
+fn set_input_cursor(f: &mut Frame<CrosstermBackend<io::Stdout>>, area: Rect, len: usize) {
+    let max_cursor_x = area.x + area.width.saturating_sub(2);
+    let cursor_x = (area.x + len as u16 + 1).min(max_cursor_x);
+    f.set_cursor(cursor_x, area.y + 1);
+}
+
+fn task_spans(
+    path: &TaskPath,
+    task: &Task,
+    now: NaiveDateTime,
+    blocked_by: &[String],
+) -> Spans<'static> {
+    let status = match task.state.as_str() {
+        workflow::DONE => "[✓]".to_string(),
+        workflow::TODO => "[ ]".to_string(),
+        state => format!("[{state}]"),
+    };
+    let base = if task.is_overdue(now) {
+        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+    } else if task.is_completed() {
+        Style::default().fg(Color::DarkGray)
+    } else {
+        Style::default()
+    };
+
+    let mut spans = vec![Span::styled(
+        format!(
+            "{}{} {} {}",
+            "  ".repeat(path.depth()),
+            path,
+            status,
+            task.description
+        ),
+        base,
+    )];
+
+    if let Some(priority) = task.priority {
+        let color = match priority {
+            Priority::High => Color::Red,
+            Priority::Medium => Color::Yellow,
+            Priority::Low => Color::Blue,
+        };
+        spans.push(Span::styled(
+            format!(" !{priority}"),
+            base.patch(Style::default().fg(color)),
+        ));
+    }
+
+    for tag in &task.tags {
+        spans.push(Span::styled(
+            format!(" +{tag}"),
+            base.patch(Style::default().fg(Color::Cyan)),
+        ));
+    }
+
+    if let Some(due) = task.due {
+        spans.push(Span::styled(format!(" due:{due}"), base));
+    }
+
+    if let Some(recurrence) = &task.recurrence {
+        spans.push(Span::styled(
+            format!(" repeat:{recurrence}"),
+            base.patch(Style::default().fg(Color::Magenta)),
+        ));
+    }
+
+    if !blocked_by.is_empty() {
+        spans.push(Span::styled(
+            format!(" (blocked by {})", blocked_by.join(", ")),
+            base.patch(Style::default().fg(Color::Yellow)),
+        ));
+    }
+
+    if let Some(time) = time_label(task) {
+        let style = if task.is_running() {
+            base.patch(Style::default().fg(Color::Green))
+        } else {
+            base
+        };
+        spans.push(Span::styled(format!(" {time}"), style));
+    }
+
+    Spans::from(spans)
+}
+
+fn detail_lines(task: &Task, short_id: &str) -> Vec<Spans<'static>> {
+    let label = |name: &str, value: String| {
+        Spans::from(vec![
+            Span::styled(
+                format!("{name:<10}"),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(value),
+        ])
+    };
+    let timestamp = |value: Option<chrono::DateTime<Local>>| {
+        value
+            .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "-".to_string())
+    };
+
+    let mut lines = vec![
+        Spans::from(Span::styled(
+            task.description.clone(),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Spans::from(""),
+        label("Id", format!("#{short_id}")),
+        label("Status", task.state.clone()),
+        label(
+            "Priority",
+            task.priority
+                .map(|priority| priority.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+        ),
+        label(
+            "Due",
+            task.due
+                .map(|due| due.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+        ),
+        label("Tags", task.tags.join(", ")),
+        label(
+            "Repeats",
+            task.recurrence
+                .as_ref()
+                .map(|recurrence| recurrence.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+        ),
+        label(
+            "Estimate",
+            task.estimate
+                .map(|estimate| estimate.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+        ),
+        label(
+            "Spent",
+            format!(
+                "{}{}",
+                timelog::format_delta(task.spent(Local::now())),
+                if task.is_running() { " (running)" } else { "" }
+            ),
+        ),
+        label("Snoozed", timestamp(task.snoozed_until)),
+        label("Created", timestamp(task.created_at)),
+        label("Completed", timestamp(task.completed_at)),
+    ];
+
+    for (number, attachment) in task.attachments.iter().enumerate() {
+        let name = if number == 0 { "Files" } else { "" };
+        lines.push(label(name, attachment.clone()));
+    }
+    if !task.note.is_empty() {
+        lines.push(Spans::from(""));
+        lines.extend(task.note.lines().map(|line| Spans::from(line.to_string())));
+    }
+
+    lines
+}
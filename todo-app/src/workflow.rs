@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+
+/// The state new and reopened tasks are in.
+pub const TODO: &str = "todo";
+/// The state of completed tasks.
+pub const DONE: &str = "done";
+
+/// The states between `todo` and `done` of new data files.
+const DEFAULT_STATES: &[&str] = &["in-progress", "review"];
+
+/// The states a task moves through, in board order. Every workflow starts
+/// with `todo` and ends with `done`; the states in between can be chosen.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub struct Workflow {
+    states: Vec<String>,
+}
+
+impl Workflow {
+    /// A workflow with `between` as the states between `todo` and `done`.
+    pub fn new<S: AsRef<str>>(between: &[S]) -> Result<Self, String> {
+        let mut states = vec![TODO.to_string()];
+
+        for state in between {
+            let state = state.as_ref().trim().to_lowercase();
+
+            if state.is_empty() || state.contains(char::is_whitespace) {
+                return Err(format!("Invalid state: \"{state}\" (use a single word)"));
+            }
+            if state == TODO || state == DONE {
+                return Err(format!(
+                    "{state} is always part of the workflow, list only the states in between"
+                ));
+            }
+            if states.contains(&state) {
+                return Err(format!("State {state} is listed twice"));
+            }
+            states.push(state);
+        }
+
+        states.push(DONE.to_string());
+        Ok(Self { states })
+    }
+
+    pub fn states(&self) -> &[String] {
+        &self.states
+    }
+
+    /// The state as written in the workflow, accepting any case.
+    pub fn find(&self, name: &str) -> Result<&str, String> {
+        let name = name.trim().to_lowercase();
+
+        self.states
+            .iter()
+            .find(|state| **state == name)
+            .map(String::as_str)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown state: {name} (expected {})",
+                    self.states.join(", ")
+                )
+            })
+    }
+
+    fn position(&self, state: &str) -> Option<usize> {
+        self.states.iter().position(|other| other == state)
+    }
+
+    /// The state after `state`, or none for `done`. A state no longer in the
+    /// workflow moves on to the first one.
+    pub fn next(&self, state: &str) -> Option<&str> {
+        let next = self.position(state).map_or(0, |position| position + 1);
+        self.states.get(next).map(String::as_str)
+    }
+
+    /// The state before `state`, or none for `todo`.
+    pub fn previous(&self, state: &str) -> Option<&str> {
+        let position = self.position(state)?;
+        self.states
+            .get(position.checked_sub(1)?)
+            .map(String::as_str)
+    }
+}
+
+impl Default for Workflow {
+    fn default() -> Self {
+        Self::new(DEFAULT_STATES).unwrap_or_else(|_| Self {
+            states: vec![TODO.to_string(), DONE.to_string()],
+        })
+    }
+}
+
+impl TryFrom<Vec<String>> for Workflow {
+    type Error = String;
+
+    fn try_from(states: Vec<String>) -> Result<Self, Self::Error> {
+        match states.as_slice() {
+            [first, between @ .., last] if first == TODO && last == DONE => Workflow::new(between),
+            _ => Err(format!(
+                "A workflow has to start with {TODO} and end with {DONE}"
+            )),
+        }
+    }
+}
+
+impl From<Workflow> for Vec<String> {
+    fn from(workflow: Workflow) -> Self {
+        workflow.states
+    }
+}
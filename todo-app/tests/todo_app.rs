@@ -0,0 +1,213 @@
+//! Drives `todo_core` the way a front-end would, over a JSON file in a
+//! temporary directory, without a terminal.
+
+use std::{env, fs, path::PathBuf};
+
+use todo_core::{
+    archive::Retention,
+    dates::Timezone,
+    storage::{JsonStore, StorageError},
+    tree::{TaskPath, TaskRef},
+    TodoApp, TodoError,
+};
+
+/// A directory of its own for each test, removed when the test ends.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new() -> Self {
+        let path = env::temp_dir().join(format!("todo-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir(&path).unwrap();
+        Self(path)
+    }
+
+    fn open(&self) -> TodoApp {
+        let store = JsonStore::new(self.0.join("tasks.json"));
+        TodoApp::new(Box::new(store), Timezone::Local).unwrap()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn descriptions(todo_app: &TodoApp) -> Vec<(String, bool)> {
+    todo_app
+        .items()
+        .iter()
+        .map(|task| (task.description.clone(), task.is_completed()))
+        .collect()
+}
+
+#[test]
+fn add_complete_undo_and_redo() {
+    let dir = TempDir::new();
+    let mut todo_app = dir.open();
+
+    let path = todo_app.add("Write release notes +docs", None).unwrap();
+    todo_app.add("Deploy api", None).unwrap();
+    todo_app.complete(&path, false).unwrap();
+    assert_eq!(
+        descriptions(&todo_app),
+        [
+            ("Write release notes".to_string(), true),
+            ("Deploy api".to_string(), false),
+        ]
+    );
+    assert_eq!(todo_app.items()[0].tags, ["docs"]);
+
+    todo_app.undo().unwrap();
+    assert!(!todo_app.items()[0].is_completed());
+    todo_app.undo().unwrap();
+    assert_eq!(todo_app.items().len(), 1);
+
+    todo_app.redo().unwrap();
+    todo_app.redo().unwrap();
+    assert!(matches!(todo_app.redo(), Err(TodoError::History(_))));
+
+    // Everything, history included, was saved as it went
+    let mut reopened = dir.open();
+    assert_eq!(descriptions(&reopened), descriptions(&todo_app));
+    reopened.undo().unwrap();
+    assert!(!reopened.items()[0].is_completed());
+}
+
+#[test]
+fn save_after_another_save_is_a_conflict() {
+    let dir = TempDir::new();
+    let mut first = dir.open();
+    first.add("Write release notes", None).unwrap();
+    let mut second = dir.open();
+    let version = second.version();
+
+    first.add("Deploy api", None).unwrap();
+    assert!(first.version() > version);
+
+    let err = second.add("Book flights", None).unwrap_err();
+    assert!(matches!(err, TodoError::Storage(StorageError::Conflict)));
+
+    // The conflict reloaded the tasks, so trying again keeps both changes
+    assert_eq!(second.items().len(), 2);
+    second.add("Book flights", None).unwrap();
+    first.reload().unwrap();
+    assert_eq!(
+        descriptions(&first),
+        [
+            ("Write release notes".to_string(), false),
+            ("Deploy api".to_string(), false),
+            ("Book flights".to_string(), false),
+        ]
+    );
+}
+
+#[test]
+fn restore_with_archive_after_keeps_the_task() {
+    let dir = TempDir::new();
+    let mut todo_app = dir.open();
+    let path = todo_app.add("Write release notes", None).unwrap();
+    todo_app.add("Deploy api", None).unwrap();
+    todo_app.complete(&path, false).unwrap();
+    assert_eq!(todo_app.archive_completed(false, None).unwrap(), 1);
+
+    todo_app.set_archive_after(Some(0));
+    let path = todo_app.restore(&TaskRef::Path(TaskPath::top(0))).unwrap();
+    assert!(todo_app.archived().is_empty());
+    assert_eq!(
+        todo_app.items()[path.last()].description,
+        "Write release notes"
+    );
+
+    assert!(dir.open().archived().is_empty());
+}
+
+#[test]
+fn replace_inline_clears_metadata_left_out() {
+    let dir = TempDir::new();
+    let mut todo_app = dir.open();
+    let path = todo_app
+        .add("Write release notes +docs !high due:2030-01-31", None)
+        .unwrap();
+
+    todo_app.edit(&path, "Write the release notes").unwrap();
+    let task = &todo_app.items()[0];
+    assert_eq!(task.tags, ["docs"]);
+    assert!(task.priority.is_some() && task.due.is_some());
+
+    let inline = task.inline().replace(" +docs", "").replace(" !high", "");
+    todo_app.replace_inline(&path, &inline).unwrap();
+    let task = &todo_app.items()[0];
+    assert_eq!(task.description, "Write the release notes");
+    assert!(task.tags.is_empty() && task.priority.is_none());
+    assert!(task.due.is_some());
+}
+
+#[test]
+fn dependencies_are_added_together_or_not_at_all() {
+    let dir = TempDir::new();
+    let mut todo_app = dir.open();
+    for description in ["Design", "Build", "Test", "Ship"] {
+        todo_app.add(description, None).unwrap();
+    }
+    let task = |position: usize| TaskRef::Path(TaskPath::top(position));
+    todo_app
+        .add_dependencies(&TaskPath::top(2), &[task(0)])
+        .unwrap();
+
+    // Design waiting for Test closes a loop, so Build is not added either
+    let err = todo_app
+        .add_dependencies(&TaskPath::top(0), &[task(1), task(2)])
+        .unwrap_err();
+    assert!(matches!(err, TodoError::InvalidInput(_)));
+    assert!(dir.open().items()[0].depends_on.is_empty());
+
+    todo_app
+        .add_dependencies(&TaskPath::top(3), &[task(1), task(2)])
+        .unwrap();
+    assert_eq!(todo_app.items()[3].depends_on.len(), 2);
+    todo_app.undo().unwrap();
+    assert!(todo_app.items()[3].depends_on.is_empty());
+}
+
+#[test]
+fn archive_and_purge_keep_positions_straight() {
+    let dir = TempDir::new();
+    let mut todo_app = dir.open();
+    for description in ["Design", "Build", "Test", "Ship", "Announce"] {
+        todo_app.add(description, None).unwrap();
+    }
+    for position in [0, 2, 3] {
+        todo_app.complete(&TaskPath::top(position), false).unwrap();
+    }
+    let archived = |todo_app: &TodoApp| -> Vec<String> {
+        todo_app
+            .archived()
+            .iter()
+            .map(|task| task.description.clone())
+            .collect()
+    };
+
+    assert_eq!(todo_app.archive_completed(false, None).unwrap(), 3);
+    assert_eq!(descriptions(&todo_app).len(), 2);
+    assert_eq!(archived(&todo_app), ["Design", "Test", "Ship"]);
+
+    todo_app.undo().unwrap();
+    assert_eq!(
+        descriptions(&todo_app)
+            .into_iter()
+            .map(|(description, _)| description)
+            .collect::<Vec<_>>(),
+        ["Design", "Build", "Test", "Ship", "Announce"]
+    );
+    todo_app.redo().unwrap();
+
+    let retention = Retention {
+        days: None,
+        keep: Some(1),
+    };
+    assert_eq!(todo_app.purge(&retention, false).unwrap(), 2);
+    assert_eq!(archived(&todo_app), ["Ship"]);
+    todo_app.undo().unwrap();
+    assert_eq!(archived(&todo_app), ["Design", "Test", "Ship"]);
+}